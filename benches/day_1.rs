@@ -0,0 +1,46 @@
+//! Day 1 over a large input, once as plain ASCII and once with a non-ASCII
+//! character on every line so the char-based path is taken instead.
+//!
+//! Run with `cargo bench --bench day_1`.
+
+use aoc_2023::day_1;
+use std::time::{Duration, Instant};
+
+const REPEAT: usize = 200;
+const ROUNDS: u32 = 5;
+
+fn time<T: PartialEq + std::fmt::Debug>(f: impl Fn() -> T) -> (T, Duration) {
+    let answer = f();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        assert_eq!(f(), answer);
+    }
+    (answer, start.elapsed() / ROUNDS)
+}
+
+fn main() {
+    let base = std::fs::read_to_string("data/day_1.txt").expect("day 1 input");
+    let ascii = base.repeat(REPEAT);
+    let unicode: String = ascii.lines().map(|line| format!("{line}é\n")).collect();
+    println!(
+        "{} lines, {} bytes ascii / {} bytes unicode",
+        ascii.lines().count(),
+        ascii.len(),
+        unicode.len()
+    );
+
+    for (name, part) in [
+        ("part 1", day_1::part_1 as fn(&str) -> _),
+        ("part 2", day_1::part_2),
+    ] {
+        let (fast, fast_time) = time(|| part(&ascii).unwrap());
+        let (slow, slow_time) = time(|| part(&unicode).unwrap());
+        assert_eq!(fast, slow);
+        println!(
+            "{name}: ascii {:>10.2?}  unicode {:>10.2?}  ({:.1}x)",
+            fast_time,
+            slow_time,
+            slow_time.as_secs_f64() / fast_time.as_secs_f64()
+        );
+    }
+}
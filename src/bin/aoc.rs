@@ -0,0 +1,508 @@
+use aoc_2023::day_2::{self, Bag, Game, GameStats};
+use aoc_2023::{get_solution, Solution, SOLUTIONS};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage:
+    aoc run --day <N> [--part <1|2>] [--input <PATH|->]
+    aoc run --all
+    aoc bags [--bag <SPEC>]... [--bag-file <PATH>]... [--input <PATH|->]
+    aoc stats [--bag <SPEC>] [--format <text|json>] [--input <PATH|->]
+    aoc check [--bag <SPEC>] [--verbose] [--input <PATH|->]
+    aoc parse --day <N> [--format json] [--input <PATH|->]
+    aoc list
+
+options:
+    --day <N>       day to run
+    --part <P>      part to run, both parts when omitted
+    --input <PATH>  puzzle input, `-` reads stdin, defaults to data/day_<N>.txt
+    --all           run both parts of every day on the bundled inputs
+    --bag <SPEC>    day 2 bag such as `red=12,green=13,blue=14`
+    --bag-file <PATH>
+                    day 2 bags as `[name]` sections of `colour = count` lines,
+                    or JSON with the `serde` feature; the puzzle bag is used
+                    when no bag is given
+    --format <FMT>  day 2 statistics as a `text` table or `json`; `parse`
+                    only writes `json` and needs the `serde` feature
+    --verbose       list the possible day 2 games too, not only the others";
+
+/// Exit code for a solution that failed on its input.
+const EXIT_FAILURE: u8 = 1;
+/// Exit code for invalid command-line arguments.
+const EXIT_USAGE: u8 = 2;
+
+#[derive(Debug, Default)]
+struct RunArgs {
+    day: Option<u8>,
+    part: Option<u8>,
+    input: Option<String>,
+    all: bool,
+}
+
+impl RunArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut run_args = RunArgs::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--day" => run_args.day = Some(parse_number(arg, args.next())?),
+                "--part" => run_args.part = Some(parse_number(arg, args.next())?),
+                "--input" => {
+                    let value = args.next().ok_or("--input requires a value")?;
+                    run_args.input = Some(value.clone());
+                }
+                "--all" => run_args.all = true,
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+
+        if run_args.all && (run_args.day.is_some() || run_args.input.is_some()) {
+            return Err("--all cannot be combined with --day or --input".to_string());
+        }
+        if !run_args.all && run_args.day.is_none() {
+            return Err("either --day or --all is required".to_string());
+        }
+        if let Some(part) = run_args.part {
+            if part != 1 && part != 2 {
+                return Err(format!("part must be 1 or 2, got {}", part));
+            }
+        }
+        Ok(run_args)
+    }
+}
+
+fn parse_number(flag: &str, value: Option<&String>) -> Result<u8, String> {
+    let value = value.ok_or_else(|| format!("{} requires a value", flag))?;
+    value
+        .parse::<u8>()
+        .map_err(|_| format!("{} expects a number, got `{}`", flag, value))
+}
+
+fn flag_value(flag: &str, value: Option<&String>) -> Result<String, String> {
+    value
+        .cloned()
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
+
+/// Reads the puzzle input from `path`, or from stdin when `path` is `-`.
+fn read_input(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        Ok(buffer)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+#[derive(Debug, Default)]
+struct BagArgs {
+    /// Bags given with `--bag`, parsed along with the arguments.
+    specs: Vec<Bag>,
+    files: Vec<String>,
+    input: Option<String>,
+}
+
+impl BagArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut bag_args = BagArgs::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bag" => bag_args
+                    .specs
+                    .push(bag_or_puzzle(Some(&flag_value(arg, args.next())?))?),
+                "--bag-file" => bag_args.files.push(flag_value(arg, args.next())?),
+                "--input" => bag_args.input = Some(flag_value(arg, args.next())?),
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+        Ok(bag_args)
+    }
+
+    /// The `--bag` bags followed by those of every `--bag-file`. A file
+    /// that cannot be read is an input error, not an argument error.
+    fn bags(&self) -> Result<Vec<Bag>, String> {
+        let mut bags = self.specs.clone();
+        for path in &self.files {
+            bags.extend(Bag::from_path(path).map_err(|err| format!("{}: {}", path, err))?);
+        }
+        if bags.is_empty() {
+            bags.push(Bag::puzzle());
+        }
+        Ok(bags)
+    }
+}
+
+/// Checks the day 2 games against every bag, printing the games each one
+/// admits.
+fn run_bags(args: BagArgs) -> Result<bool, String> {
+    let bags = match args.bags() {
+        Ok(bags) => bags,
+        Err(err) => {
+            eprintln!("day 2: failed to read bags from {}", err);
+            return Ok(false);
+        }
+    };
+    let path = args.input.unwrap_or_else(|| day_2::DayTwo.input_path());
+    let input = match read_input(&path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("day 2: failed to read {}: {}", path, err);
+            return Ok(false);
+        }
+    };
+
+    match day_2::evaluate_bags(input.as_bytes(), &bags) {
+        Ok(admitted) => {
+            for (bag, ids) in bags.iter().zip(admitted) {
+                let listed: Vec<String> = ids.iter().map(u32::to_string).collect();
+                println!(
+                    "{}: {} games, id sum {}: {}",
+                    bag,
+                    ids.len(),
+                    ids.iter().map(|&id| u64::from(id)).sum::<u64>(),
+                    listed.join(" ")
+                );
+            }
+            Ok(true)
+        }
+        Err(err) => {
+            eprintln!("day 2 failed: {}", err);
+            Ok(false)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct StatsArgs {
+    bag: Option<String>,
+    json: bool,
+    input: Option<String>,
+}
+
+impl StatsArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut stats_args = StatsArgs::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bag" => stats_args.bag = Some(flag_value(arg, args.next())?),
+                "--input" => stats_args.input = Some(flag_value(arg, args.next())?),
+                "--format" => match flag_value(arg, args.next())?.as_str() {
+                    "text" => stats_args.json = false,
+                    "json" => stats_args.json = true,
+                    other => return Err(format!("unknown format `{}`", other)),
+                },
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+        Ok(stats_args)
+    }
+}
+
+/// The bag given with `--bag`, or the puzzle bag.
+fn bag_or_puzzle(spec: Option<&String>) -> Result<Bag, String> {
+    match spec {
+        Some(spec) => Bag::from_spec(spec).map_err(|err| format!("--bag `{}`: {}", spec, err)),
+        None => Ok(Bag::puzzle()),
+    }
+}
+
+/// Reads and parses the day 2 games, reporting a failure on stderr.
+fn load_games(input: Option<String>) -> Option<Vec<Game>> {
+    let path = input.unwrap_or_else(|| day_2::DayTwo.input_path());
+    let parsed = match read_input(&path) {
+        Ok(input) => day_2::parse_games(input.as_bytes()),
+        Err(err) => {
+            eprintln!("day 2: failed to read {}: {}", path, err);
+            return None;
+        }
+    };
+    parsed
+        .map_err(|err| eprintln!("day 2 failed: {}", err))
+        .ok()
+}
+
+/// Prints the day 2 statistics report.
+fn run_stats(args: StatsArgs) -> Result<bool, String> {
+    let bag = bag_or_puzzle(args.bag.as_ref())?;
+    let Some(games) = load_games(args.input) else {
+        return Ok(false);
+    };
+    let stats = GameStats::new(&games, &bag);
+    if args.json {
+        println!("{}", stats.to_json());
+    } else {
+        print!("{}", stats);
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Default)]
+struct CheckArgs {
+    bag: Option<String>,
+    verbose: bool,
+    input: Option<String>,
+}
+
+impl CheckArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut check_args = CheckArgs::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bag" => check_args.bag = Some(flag_value(arg, args.next())?),
+                "--input" => check_args.input = Some(flag_value(arg, args.next())?),
+                "--verbose" => check_args.verbose = true,
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+        Ok(check_args)
+    }
+}
+
+/// Explains why each day 2 game the bag does not admit is impossible.
+fn run_check(args: CheckArgs) -> Result<bool, String> {
+    let bag = bag_or_puzzle(args.bag.as_ref())?;
+    let Some(games) = load_games(args.input) else {
+        return Ok(false);
+    };
+    for game in &games {
+        let validation = game.validate(bag.cubes());
+        if args.verbose || !validation.is_valid() {
+            println!("{}", validation);
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Default)]
+// without serde the arguments are checked but never used
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+struct ParseArgs {
+    day: u8,
+    input: Option<String>,
+}
+
+impl ParseArgs {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut day = None;
+        let mut input = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--day" => day = Some(parse_number(arg, args.next())?),
+                "--input" => input = Some(flag_value(arg, args.next())?),
+                "--format" => match flag_value(arg, args.next())?.as_str() {
+                    "json" => {}
+                    other => return Err(format!("unknown format `{}`", other)),
+                },
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+        let day = day.ok_or("--day is required")?;
+        Ok(ParseArgs { day, input })
+    }
+}
+
+/// Prints the parsed model of a day's input as JSON.
+#[cfg(feature = "serde")]
+fn run_parse(args: ParseArgs) -> Result<bool, String> {
+    use aoc_2023::error::AocError;
+    use aoc_2023::{day_1, day_3, day_4};
+
+    fn to_json<T: serde::Serialize>(parsed: Result<T, AocError>) -> Result<String, AocError> {
+        serde_json::to_string(&parsed?).map_err(|err| AocError::Validation(err.to_string()))
+    }
+
+    let solution =
+        get_solution(args.day).ok_or_else(|| format!("day {} is not implemented", args.day))?;
+    let path = args.input.unwrap_or_else(|| solution.input_path());
+    let input = match read_input(&path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("day {}: failed to read {}: {}", args.day, path, err);
+            return Ok(false);
+        }
+    };
+
+    let reader = input.as_bytes();
+    let json = match args.day {
+        1 => to_json(day_1::decode_lines(
+            reader,
+            &day_1::DigitVocabulary::default(),
+        )),
+        2 => to_json(day_2::parse_games(reader)),
+        3 => to_json(day_3::EngineSchematic::from_reader(reader)),
+        4 => to_json(day_4::parse_cards(reader)),
+        day => return Err(format!("day {} has no parsed model", day)),
+    };
+    match json {
+        Ok(json) => {
+            println!("{}", json);
+            Ok(true)
+        }
+        Err(err) => {
+            eprintln!("day {} failed: {}", args.day, err);
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn run_parse(_: ParseArgs) -> Result<bool, String> {
+    Err("parse needs the `serde` feature, build with `--features serde`".to_string())
+}
+
+/// Runs the requested parts, printing each answer. Returns whether all of
+/// them succeeded.
+fn run_parts(solution: &dyn Solution, parts: &[u8], path: &str) -> bool {
+    let input = match read_input(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("day {}: failed to read {}: {}", solution.day(), path, err);
+            return false;
+        }
+    };
+
+    let mut ok = true;
+    for &part in parts {
+        match solution.part(part, &input) {
+            Some(Ok(answer)) => println!("day {} part {}: {}", solution.day(), part, answer),
+            Some(Err(err)) => {
+                eprintln!("day {} part {} failed: {}", solution.day(), part, err);
+                ok = false;
+            }
+            None => unreachable!("part numbers are validated"),
+        }
+    }
+    ok
+}
+
+fn run(args: RunArgs) -> Result<bool, String> {
+    let parts: Vec<u8> = match args.part {
+        Some(part) => vec![part],
+        None => vec![1, 2],
+    };
+
+    if args.all {
+        let mut ok = true;
+        for solution in SOLUTIONS {
+            ok &= run_parts(*solution, &parts, &solution.input_path());
+        }
+        return Ok(ok);
+    }
+
+    let day = args.day.expect("day is validated");
+    let solution = get_solution(day).ok_or_else(|| format!("day {} is not implemented", day))?;
+    let path = args.input.unwrap_or_else(|| solution.input_path());
+    Ok(run_parts(solution, &parts, &path))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("run") => RunArgs::parse(&args[1..]).and_then(run),
+        Some("bags") => BagArgs::parse(&args[1..]).and_then(run_bags),
+        Some("stats") => StatsArgs::parse(&args[1..]).and_then(run_stats),
+        Some("check") => CheckArgs::parse(&args[1..]).and_then(run_check),
+        Some("parse") => ParseArgs::parse(&args[1..]).and_then(run_parse),
+        Some("list") => {
+            for solution in SOLUTIONS {
+                println!("day {}", solution.day());
+            }
+            Ok(true)
+        }
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        Some(command) => Err(format!("unknown command `{}`", command)),
+        None => Err("missing command".to_string()),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_FAILURE),
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn run_args_parse() {
+        let run_args = RunArgs::parse(&args("--day 3 --part 2 --input -")).unwrap();
+        assert_eq!(run_args.day, Some(3));
+        assert_eq!(run_args.part, Some(2));
+        assert_eq!(run_args.input.as_deref(), Some("-"));
+        assert!(!run_args.all);
+
+        assert!(RunArgs::parse(&args("--all")).unwrap().all);
+    }
+
+    #[test]
+    fn run_args_reject_invalid() {
+        assert!(RunArgs::parse(&args("")).is_err());
+        assert!(RunArgs::parse(&args("--day")).is_err());
+        assert!(RunArgs::parse(&args("--day x")).is_err());
+        assert!(RunArgs::parse(&args("--day 1 --part 3")).is_err());
+        assert!(RunArgs::parse(&args("--all --day 1")).is_err());
+        assert!(RunArgs::parse(&args("--day 1 --verbose")).is_err());
+    }
+
+    #[test]
+    fn stats_args_parse() {
+        let stats_args = StatsArgs::parse(&args("--format json --bag red=1")).unwrap();
+        assert!(stats_args.json);
+        assert_eq!(stats_args.bag.as_deref(), Some("red=1"));
+        assert!(StatsArgs::parse(&args("--format yaml")).is_err());
+    }
+
+    #[test]
+    fn check_args_parse() {
+        let check_args = CheckArgs::parse(&args("--verbose --input -")).unwrap();
+        assert!(check_args.verbose);
+        assert_eq!(check_args.input.as_deref(), Some("-"));
+        assert!(CheckArgs::parse(&args("--format json")).is_err());
+    }
+
+    #[test]
+    fn parse_args_parse() {
+        let parse_args = ParseArgs::parse(&args("--day 4 --format json")).unwrap();
+        assert_eq!(parse_args.day, 4);
+        assert!(ParseArgs::parse(&args("--format json")).is_err());
+        assert!(ParseArgs::parse(&args("--day 4 --format text")).is_err());
+    }
+
+    #[test]
+    fn bag_args_parse() {
+        let bag_args =
+            BagArgs::parse(&args("--bag red=1 --bag-file bags.txt --bag blue=2")).unwrap();
+        let names: Vec<&str> = bag_args.specs.iter().map(Bag::name).collect();
+        assert_eq!(names, vec!["red=1", "blue=2"]);
+        assert_eq!(bag_args.files, vec!["bags.txt"]);
+        assert!(bag_args
+            .bags()
+            .unwrap_err()
+            .starts_with("bags.txt: i/o error"));
+
+        assert_eq!(BagArgs::default().bags().unwrap(), vec![Bag::puzzle()]);
+        assert!(BagArgs::parse(&args("--bag")).is_err());
+        assert!(BagArgs::parse(&args("--bag red")).is_err());
+    }
+}
@@ -1,93 +1,39 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufRead};
+use crate::error::AocError;
+use crate::input;
+use crate::solution::{Solution, SolutionResult};
+use std::io::BufRead;
 
 // solution 2
 // construct a trie of digits
-// go from start and end and send substrings
-// once both searches yield a valid result, convert to digits
-
-#[derive(Debug)]
-pub enum DayOneError {
-    ParsingError,
-}
-
-#[derive(Default)]
-pub struct TrieNode {
-    children: HashMap<char, TrieNode>,
-    is_end_of_word: bool,
-}
-
-pub struct Trie {
-    root: TrieNode,
-}
-
-impl Trie {
-    pub fn new() -> Self {
-        Trie {
-            root: TrieNode::default(),
-        }
-    }
-
+// add failure links to turn it into an aho-corasick automaton
+// scan the line once, the first and last reported matches are the digits
+//
+// ascii lines take a byte path instead: no char vector, a trie with children
+// indexed by byte, and scanning from either end for the first and last digit
+
+mod ascii;
+mod calibration;
+mod rule;
+mod vocabulary;
+
+pub use crate::trie::{AhoCorasick, Match, ReversedTrie, Trie};
+pub use ascii::AsciiMatcher;
+pub use calibration::{CalibrationLine, DigitToken, TokenKind};
+pub use rule::{CalibrationRule, Concatenated, DigitSum, FirstLast};
+pub use vocabulary::{DigitVocabulary, Language};
+
+impl Trie<u32> {
+    /// Numerals and English words for the digits one to nine, mapped to
+    /// their value.
     pub fn new_digits() -> Self {
-        let mut trie = Trie::new();
-
-        for word in [
-            "1", "2", "3", "4", "5", "6", "7", "8", "9", "one", "two", "three", "four", "five",
-            "six", "seven", "eight", "nine",
-        ]
-        .iter()
-        {
-            trie.insert(word);
-        }
-        trie
-    }
-
-    pub fn insert(&mut self, word: &str) {
-        let mut node = &mut self.root;
-        for ch in word.chars() {
-            node = node.children.entry(ch).or_insert_with(TrieNode::default);
-        }
-        node.is_end_of_word = true;
-    }
-
-    pub fn search(&self, word: &str) -> bool {
-        let mut node = &self.root;
-        for ch in word.chars() {
-            match node.children.get(&ch) {
-                Some(next_node) => node = next_node,
-                None => return false,
-            }
-        }
-        node.is_end_of_word
-    }
-
-    pub fn contains_word(&self, s: &str) -> bool {
-        for (start_index, _) in s.char_indices() {
-            if self.search_from(&s[start_index..]) {
-                return true;
-            }
-        }
-        false
-    }
-
-    pub fn search_from(&self, s: &str) -> bool {
-        let mut node = &self.root;
-        for ch in s.chars() {
-            match node.children.get(&ch) {
-                Some(next_node) => node = next_node,
-                None => return false,
-            }
-            if node.is_end_of_word {
-                return true;
-            }
-        }
-        false
+        DigitVocabulary::default().trie()
     }
 }
 
-fn get_first_last_digit_as_u32(s: &[char]) -> u32 {
-    // guaranteed to contain two digits
+fn get_first_last_digit_as_u32(s: &[char]) -> Option<u32> {
+    if !s.iter().any(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
     let mut first: Option<char> = None;
     let mut last: Option<char> = None;
     let mut start: usize = 0;
@@ -96,11 +42,11 @@ fn get_first_last_digit_as_u32(s: &[char]) -> u32 {
         let curr_first = s[start];
         let curr_last = s[end];
 
-        if curr_first.is_digit(10) {
+        if curr_first.is_ascii_digit() {
             first = Some(curr_first);
         }
 
-        if curr_last.is_digit(10) {
+        if curr_last.is_ascii_digit() {
             last = Some(curr_last);
         }
 
@@ -115,127 +61,251 @@ fn get_first_last_digit_as_u32(s: &[char]) -> u32 {
             break;
         }
     }
-    let first = first.expect("first should have been found");
-    let last = last.expect("last should have been found");
-    let concatenated = first.to_string() + &last.to_string();
-    concatenated.parse::<u32>().expect("parsing failed")
+    let first = first?.to_digit(10)?;
+    let last = last?.to_digit(10)?;
+    Some(first * 10 + last)
 }
 
-fn str_to_char_digit(s: &str) -> Option<char> {
-    match s {
-        "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => s.chars().next(),
-        "one" => Some('1'),
-        "two" => Some('2'),
-        "three" => Some('3'),
-        "four" => Some('4'),
-        "five" => Some('5'),
-        "six" => Some('6'),
-        "seven" => Some('7'),
-        "eight" => Some('8'),
-        "nine" => Some('9'),
-        _ => None,
-    }
+fn get_first_and_last_digit_include_words(s: &str, automaton: &AhoCorasick<u32>) -> Option<u32> {
+    CalibrationLine::decode(s, automaton).calibration_value()
 }
 
-fn get_first_and_last_digit_include_words(s: &str) -> u32 {
-    let mut left_start: usize = 0;
-    let mut left_end: usize = left_start;
+fn no_digit_error(line: &str, line_number: usize) -> AocError {
+    AocError::parse_in(line, line, "line contains no digit").at_line(line_number)
+}
 
-    let mut right_end: usize = s.len(); // exclusive take so no minus one
-    let mut right_start: usize = right_end;
+/// What to do with a line that contains no digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoDigitPolicy {
+    /// Leave the line out.
+    Skip,
+    /// Count the line with a calibration value of zero.
+    Zero,
+    /// Fail with a parse error pointing at the line.
+    #[default]
+    Error,
+}
 
-    let mut left_contained_found = false;
-    let mut right_contained_found = false;
+impl NoDigitPolicy {
+    /// Value to use for a digit-free line, `None` to skip it.
+    fn resolve(self, line: &str, line_number: usize) -> Result<Option<u64>, AocError> {
+        match self {
+            NoDigitPolicy::Skip => Ok(None),
+            NoDigitPolicy::Zero => Ok(Some(0)),
+            NoDigitPolicy::Error => Err(no_digit_error(line, line_number)),
+        }
+    }
+}
 
-    let trie = Trie::new_digits();
+/// Adds the value of line `line_number` to a running total, failing when
+/// the total no longer fits in a `u64`.
+fn add_line(total: u64, num: u64, line_number: usize) -> Result<u64, AocError> {
+    total
+        .checked_add(num)
+        .ok_or_else(|| AocError::Validation(format!("total overflows u64 at line {}", line_number)))
+}
 
-    while !left_contained_found || !right_contained_found {
-        // the string won't be empty so we start by incrementing to make
-        // it len 1
-        if !left_contained_found {
-            left_end += 1;
+/// Sums `line_value` over every line, applying `policy` where it finds no
+/// digit.
+fn sum_lines<R: BufRead>(
+    reader: R,
+    policy: NoDigitPolicy,
+    mut line_value: impl FnMut(&str) -> Option<u32>,
+) -> Result<u64, AocError> {
+    let mut total: u64 = 0;
+    input::for_each_line(reader, |line_number, ip| {
+        let num = match line_value(ip) {
+            Some(num) => u64::from(num),
+            None => policy.resolve(ip, line_number)?.unwrap_or(0),
+        };
+        total = add_line(total, num, line_number)?;
+        Ok(())
+    })?;
+
+    Ok(total)
+}
+
+pub fn part_1_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    part_1_from_reader_with_policy(reader, NoDigitPolicy::Error)
+}
+
+pub fn part_1_from_reader_with_policy<R: BufRead>(
+    reader: R,
+    policy: NoDigitPolicy,
+) -> Result<u64, AocError> {
+    sum_lines(reader, policy, |ip| {
+        if ip.is_ascii() {
+            return ascii::first_last_numerals(ip.as_bytes());
         }
-        if !right_contained_found {
-            right_start -= 1;
+        let chars_ip: Vec<char> = ip.chars().collect();
+        get_first_last_digit_as_u32(&chars_ip)
+    })
+}
+
+pub fn part_2_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    part_2_from_reader_with_vocabulary(reader, &DigitVocabulary::default(), NoDigitPolicy::Error)
+}
+
+/// Part 2 with digits spelled out in the words of `vocabulary`.
+pub fn part_2_from_reader_with_vocabulary<R: BufRead>(
+    reader: R,
+    vocabulary: &DigitVocabulary,
+    policy: NoDigitPolicy,
+) -> Result<u64, AocError> {
+    let matcher = AsciiMatcher::new(vocabulary);
+    let trie = vocabulary.trie();
+    let automaton = trie.automaton();
+    sum_lines(reader, policy, |ip| {
+        if ip.is_ascii() {
+            return matcher.calibration_value(ip.as_bytes());
         }
+        get_first_and_last_digit_include_words(ip, &automaton)
+    })
+}
 
-        let left_subslice = &s[left_start..left_end];
-        let right_subslice = &s[right_start..right_end];
+pub fn part_1(input: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input.as_bytes())
+}
 
-        left_contained_found = trie.contains_word(&left_subslice);
-        right_contained_found = trie.contains_word(&right_subslice);
-    }
+pub fn part_2(input: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input.as_bytes())
+}
 
-    let mut left_found = false;
-    let mut right_found = false;
+pub fn part_1_with_policy(input: &str, policy: NoDigitPolicy) -> Result<u64, AocError> {
+    part_1_from_reader_with_policy(input.as_bytes(), policy)
+}
 
-    while !left_found || !right_found {
-        let left_subslice = &s[left_start..left_end];
-        let right_subslice = &s[right_start..right_end];
+pub fn part_2_with_policy(input: &str, policy: NoDigitPolicy) -> Result<u64, AocError> {
+    part_2_from_reader_with_vocabulary(input.as_bytes(), &DigitVocabulary::default(), policy)
+}
 
-        left_found = trie.search(left_subslice);
-        right_found = trie.search(right_subslice);
+pub fn part_2_with_vocabulary(input: &str, vocabulary: &DigitVocabulary) -> Result<u64, AocError> {
+    part_2_from_reader_with_vocabulary(input.as_bytes(), vocabulary, NoDigitPolicy::Error)
+}
 
-        if !left_found {
-            left_start += 1;
-        }
-        if !right_found {
-            right_end -= 1;
+/// Calibration value of every line paired with its line number. Digit-free
+/// lines are handled according to `policy`, skipped lines are left out.
+pub fn calibration_values<R: BufRead>(
+    reader: R,
+    vocabulary: &DigitVocabulary,
+    rule: &dyn CalibrationRule,
+    policy: NoDigitPolicy,
+) -> Result<Vec<(usize, u64)>, AocError> {
+    let trie = vocabulary.trie();
+    let automaton = trie.automaton();
+
+    let mut values = vec![];
+    for line in input::numbered_lines(reader) {
+        let (line_number, ip) = line?;
+        let decoded = CalibrationLine::decode(&ip, &automaton);
+        let value = match rule.value(&decoded)? {
+            Some(value) => Some(value),
+            None => policy.resolve(&ip, line_number)?,
+        };
+        if let Some(value) = value {
+            values.push((line_number, value));
         }
     }
+    Ok(values)
+}
 
-    let left_digit_text = &s[left_start..left_end];
-    let right_digit_text = &s[right_start..right_end];
+/// Every line decoded into its digit tokens.
+pub fn decode_lines<R: BufRead>(
+    reader: R,
+    vocabulary: &DigitVocabulary,
+) -> Result<Vec<CalibrationLine>, AocError> {
+    let trie = vocabulary.trie();
+    let automaton = trie.automaton();
+
+    let mut lines = vec![];
+    input::for_each_line(reader, |_, ip| {
+        lines.push(CalibrationLine::decode(ip, &automaton));
+        Ok(())
+    })?;
+    Ok(lines)
+}
 
-    let left_digit = str_to_char_digit(left_digit_text).expect("failed to parse left digit");
-    let rigth_digit = str_to_char_digit(right_digit_text).expect("failed to parse right digit");
+/// Sums the calibration values `rule` computes from every line, with digits
+/// spelled out in the words of `vocabulary`.
+pub fn calibrate<R: BufRead>(
+    reader: R,
+    vocabulary: &DigitVocabulary,
+    rule: &dyn CalibrationRule,
+    policy: NoDigitPolicy,
+) -> Result<u64, AocError> {
+    let mut total: u64 = 0;
+    for (line_number, num) in calibration_values(reader, vocabulary, rule, policy)? {
+        total = add_line(total, num, line_number)?;
+    }
 
-    let concatenated = left_digit.to_string() + &rigth_digit.to_string();
-    concatenated
-        .parse::<u32>()
-        .expect("parsing of digit failed")
+    Ok(total)
 }
 
-pub fn solution_part_1(filename: &str) -> Result<u32, DayOneError> {
-    let maybe_file = File::open(filename);
+/// Part 1, numerals only, with a custom rule.
+pub fn part_1_with_rule(input: &str, rule: &dyn CalibrationRule) -> Result<u64, AocError> {
+    calibrate(
+        input.as_bytes(),
+        &DigitVocabulary::numerals_only(),
+        rule,
+        NoDigitPolicy::Error,
+    )
+}
 
-    match maybe_file {
-        Ok(file) => {
-            let lines = io::BufReader::new(file).lines();
+/// Part 2, numerals and English words, with a custom rule.
+pub fn part_2_with_rule(input: &str, rule: &dyn CalibrationRule) -> Result<u64, AocError> {
+    calibrate(
+        input.as_bytes(),
+        &DigitVocabulary::default(),
+        rule,
+        NoDigitPolicy::Error,
+    )
+}
 
-            let mut total: u32 = 0;
-            for line in lines {
-                if let Ok(ip) = line {
-                    let chars_ip: Vec<char> = ip.chars().collect();
-                    let num = get_first_last_digit_as_u32(&chars_ip);
-                    total += num;
-                }
-            }
+pub fn solution_part_1(filename: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input::open(filename)?)
+}
 
-            Ok(total)
-        }
-        Err(_) => Err(DayOneError::ParsingError),
-    }
+pub fn solution_part_2(filename: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input::open(filename)?)
 }
 
-pub fn solution_part_2(filename: &str) -> Result<u32, DayOneError> {
-    let maybe_file = File::open(filename);
+pub fn solution_part_1_with_rule(
+    filename: &str,
+    rule: &dyn CalibrationRule,
+) -> Result<u64, AocError> {
+    calibrate(
+        input::open(filename)?,
+        &DigitVocabulary::numerals_only(),
+        rule,
+        NoDigitPolicy::Error,
+    )
+}
 
-    match maybe_file {
-        Ok(file) => {
-            let lines = io::BufReader::new(file).lines();
+pub fn solution_part_2_with_rule(
+    filename: &str,
+    rule: &dyn CalibrationRule,
+) -> Result<u64, AocError> {
+    calibrate(
+        input::open(filename)?,
+        &DigitVocabulary::default(),
+        rule,
+        NoDigitPolicy::Error,
+    )
+}
 
-            let mut total: u32 = 0;
-            for line in lines {
-                if let Ok(ip) = line {
-                    let num = get_first_and_last_digit_include_words(&ip);
-                    total += num;
-                }
-            }
+pub struct DayOne;
 
-            Ok(total)
-        }
-        Err(_) => Err(DayOneError::ParsingError),
+impl Solution for DayOne {
+    fn day(&self) -> u8 {
+        1
+    }
+
+    fn part_one(&self, input: &str) -> SolutionResult {
+        Ok(part_1(input)?.into())
+    }
+
+    fn part_two(&self, input: &str) -> SolutionResult {
+        Ok(part_2(input)?.into())
     }
 }
 
@@ -248,17 +318,160 @@ mod tests {
         let s = "sd3fklsdj2";
         let chars: Vec<char> = s.chars().collect();
         let num = get_first_last_digit_as_u32(&chars);
-        assert_eq!(num, 32);
+        assert_eq!(num, Some(32));
 
         let s = "sd3fslkdfjsdkljf239klsdj2";
         let chars: Vec<char> = s.chars().collect();
         let num = get_first_last_digit_as_u32(&chars);
-        assert_eq!(num, 32);
+        assert_eq!(num, Some(32));
 
         let s = "treb7uchet";
         let chars: Vec<char> = s.chars().collect();
         let num = get_first_last_digit_as_u32(&chars);
-        assert_eq!(num, 77);
+        assert_eq!(num, Some(77));
+    }
+
+    #[test]
+    fn first_last_digit_missing() {
+        let chars: Vec<char> = "abc".chars().collect();
+        assert_eq!(get_first_last_digit_as_u32(&chars), None);
+        assert_eq!(get_first_last_digit_as_u32(&[]), None);
+
+        let trie = Trie::new_digits();
+        let automaton = trie.automaton();
+        assert_eq!(
+            get_first_and_last_digit_include_words("xyz", &automaton),
+            None
+        );
+        assert_eq!(get_first_and_last_digit_include_words("", &automaton), None);
+    }
+
+    #[test]
+    fn day_1_examples() {
+        let example_1 = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\n";
+        assert_eq!(part_1(example_1).unwrap(), 142);
+
+        let example_2 = "two1nine\neightwothree\nabcone2threexyz\nxtwone3four\n\
+                         4nineeightseven2\nzoneight234\n7pqrstsixteen\n";
+        assert_eq!(part_2(example_2).unwrap(), 281);
+    }
+
+    #[test]
+    fn day_1_other_languages() {
+        let german = DigitVocabulary::builtin(Language::German);
+        assert_eq!(
+            part_2_with_vocabulary("zweiundfünfzig\n", &german).unwrap(),
+            25
+        );
+
+        let czech = DigitVocabulary::builtin(Language::Czech);
+        assert_eq!(
+            part_2_with_vocabulary("čtyřicet tři\nsedmdesát\n", &czech).unwrap(),
+            // `čtyřicet` starts with `čtyři`
+            43 + 77
+        );
+
+        let mut bilingual = DigitVocabulary::builtin(Language::Spanish);
+        bilingual.extend(&DigitVocabulary::builtin(Language::French));
+        assert_eq!(
+            part_2_with_vocabulary("dos-huit\n", &bilingual).unwrap(),
+            28
+        );
+
+        // english words are not digits under a spanish vocabulary
+        let spanish = DigitVocabulary::builtin(Language::Spanish);
+        assert_eq!(part_2_with_vocabulary("one7nine\n", &spanish).unwrap(), 77);
+    }
+
+    #[test]
+    fn day_1_with_rules() {
+        let example_1 = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet\n";
+        assert_eq!(part_1_with_rule(example_1, &FirstLast).unwrap(), 142);
+        assert_eq!(
+            part_1_with_rule(example_1, &Concatenated).unwrap(),
+            12 + 38 + 12345 + 7
+        );
+        assert_eq!(
+            part_1_with_rule(example_1, &DigitSum).unwrap(),
+            3 + 11 + 15 + 7
+        );
+
+        assert_eq!(part_2_with_rule("two1nine\n", &DigitSum).unwrap(), 12);
+        assert!(part_2_with_rule("nothing\n", &FirstLast).is_err());
+
+        let part_1 = solution_part_1("data/day_1.txt").unwrap();
+        let part_2 = solution_part_2("data/day_1.txt").unwrap();
+        assert_eq!(
+            solution_part_1_with_rule("data/day_1.txt", &FirstLast).unwrap(),
+            part_1
+        );
+        assert_eq!(
+            solution_part_2_with_rule("data/day_1.txt", &FirstLast).unwrap(),
+            part_2
+        );
+    }
+
+    #[test]
+    fn day_1_total_overflow_is_an_error() {
+        assert_eq!(add_line(1, 2, 1).unwrap(), 3);
+        assert!(matches!(
+            add_line(u64::MAX, 1, 7),
+            Err(AocError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn day_1_rule_agrees_with_part_1_on_zero() {
+        for input in ["0abc5\n", "a0b\n", "x0y9z0\n5\n"] {
+            assert_eq!(
+                part_1_with_rule(input, &FirstLast).unwrap(),
+                part_1(input).unwrap(),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn day_1_no_digit_policies() {
+        // empty, whitespace-only and digit-free lines
+        let input = "1abc2\n\n   \t\nabcdef\ntreb7uchet\n";
+        for part in [part_1_with_policy, part_2_with_policy] {
+            assert_eq!(part(input, NoDigitPolicy::Skip).unwrap(), 12 + 77);
+            assert_eq!(part(input, NoDigitPolicy::Zero).unwrap(), 12 + 77);
+            let err = part(input, NoDigitPolicy::Error).unwrap_err();
+            assert!(matches!(err, AocError::Parse { line: 2, .. }));
+        }
+
+        let err = part_1_with_policy("1\n   \n", NoDigitPolicy::Error).unwrap_err();
+        assert!(matches!(err, AocError::Parse { line: 2, .. }));
+        let err = part_2_with_policy("one\nxyz\n", NoDigitPolicy::Error).unwrap_err();
+        assert!(matches!(err, AocError::Parse { line: 2, .. }));
+    }
+
+    #[test]
+    fn day_1_calibration_values_policies() {
+        let input = "two\n\nx3y\n";
+        let vocabulary = DigitVocabulary::default();
+        let values = |policy| calibration_values(input.as_bytes(), &vocabulary, &FirstLast, policy);
+        assert_eq!(values(NoDigitPolicy::Skip).unwrap(), vec![(1, 22), (3, 33)]);
+        assert_eq!(
+            values(NoDigitPolicy::Zero).unwrap(),
+            vec![(1, 22), (2, 0), (3, 33)]
+        );
+        assert!(values(NoDigitPolicy::Error).is_err());
+    }
+
+    #[test]
+    fn day_1_reports_line_number() {
+        let err = part_1("1abc2\nnothing\n").err().unwrap();
+        assert!(matches!(err, AocError::Parse { line: 2, .. }));
+    }
+
+    #[test]
+    fn day_1_missing_file_is_io_error() {
+        let result = solution_part_1("data/does_not_exist.txt");
+        assert!(matches!(result, Err(AocError::Io(_))));
     }
 
     #[test]
@@ -279,7 +492,7 @@ mod tests {
         ]
         .iter()
         {
-            trie.insert(word);
+            trie.insert(word, ());
         }
 
         let is_number = trie.search("two");
@@ -300,7 +513,7 @@ mod tests {
         ]
         .iter()
         {
-            trie.insert(word);
+            trie.insert(word, ());
         }
 
         let is_number = trie.contains_word("sdfsdtwosdfs");
@@ -313,29 +526,124 @@ mod tests {
         assert!(!not_number);
     }
 
+    #[test]
+    fn aho_corasick_finds_overlapping_digits() {
+        let digits = Trie::new_digits();
+        let automaton = digits.automaton();
+        let matches: Vec<(usize, usize, u32)> = automaton
+            .find_all("oneight2")
+            .iter()
+            .map(|m| (m.start, m.end, *m.value))
+            .collect();
+        assert_eq!(matches, vec![(0, 3, 1), (2, 7, 8), (7, 8, 2)]);
+    }
+
+    #[test]
+    fn reversed_trie_finds_last_match() {
+        let digits = Trie::new_digits();
+        let reversed = digits.reversed();
+        assert!(reversed.search("seven"));
+        assert!(!reversed.search("neves"));
+
+        for (s, last) in [
+            ("eightwo", "two"),
+            ("oneight", "eight"),
+            ("xtwone3four", "four"),
+            ("7pqrstsixteen", "six"),
+            ("zoneight234", "4"),
+        ] {
+            let found = reversed.rfind_match(s).unwrap();
+            assert_eq!(found.as_str(s), last);
+        }
+        assert!(reversed.rfind_match("abc").is_none());
+        assert!(reversed.rfind_match("").is_none());
+
+        let automaton = digits.automaton();
+        for s in [
+            "eightwo",
+            "oneight",
+            "twone3four",
+            "sevenine",
+            "9oneighthree",
+        ] {
+            let found = reversed.rfind_match(s).unwrap();
+            let last = CalibrationLine::decode(s, &automaton)
+                .last()
+                .cloned()
+                .unwrap();
+            assert_eq!(
+                (*found.value, found.start..found.end),
+                (last.value, last.span)
+            );
+        }
+    }
+
+    #[test]
+    fn trie_find_match_and_words() {
+        let digits = Trie::new_digits();
+        let found = digits.find_match("xeightwo").unwrap();
+        assert_eq!(
+            found,
+            Match {
+                start: 1,
+                end: 6,
+                value: &8
+            }
+        );
+        assert_eq!(digits.get("seven"), Some(&7));
+
+        let words = digits.words();
+        assert_eq!(words.len(), 18);
+        assert_eq!(words[0], "1");
+        assert_eq!(words[17], "two");
+    }
+
+    #[test]
+    fn reversed_trie_multibyte() {
+        let mut reversed = ReversedTrie::new();
+        reversed.insert("tři", 3);
+        let s = "tři, dva";
+        let found = reversed.rfind_match(s).unwrap();
+        assert_eq!((found.start, found.end, *found.value), (0, 4, 3));
+    }
+
+    #[test]
+    fn aho_corasick_reports_byte_positions() {
+        let mut trie = Trie::new();
+        trie.insert("čtyři", 4);
+        let automaton = trie.automaton();
+        let haystack = "ač čtyři";
+        let matches = automaton.find_all(haystack);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].as_str(haystack), "čtyři");
+    }
+
     #[test]
     fn get_first_and_last_digit_inlude_words_success() {
+        let trie = Trie::new_digits();
+        let automaton = trie.automaton();
+
         let s = "eightwothree";
-        let num = get_first_and_last_digit_include_words(s);
-        assert_eq!(num, 83);
+        let num = get_first_and_last_digit_include_words(s, &automaton);
+        assert_eq!(num, Some(83));
 
         let s = "eightwothree5schmee";
-        let num = get_first_and_last_digit_include_words(s);
-        assert_eq!(num, 85);
+        let num = get_first_and_last_digit_include_words(s, &automaton);
+        assert_eq!(num, Some(85));
 
         let s = "ei7ghtwothree5schmee";
-        let num = get_first_and_last_digit_include_words(s);
-        assert_eq!(num, 75);
+        let num = get_first_and_last_digit_include_words(s, &automaton);
+        assert_eq!(num, Some(75));
 
         let s = "xdtwoxd";
-        let num = get_first_and_last_digit_include_words(s);
-        assert_eq!(num, 22);
+        let num = get_first_and_last_digit_include_words(s, &automaton);
+        assert_eq!(num, Some(22));
 
         let s = "six9mnfjmtsf2kfmznkxntninesevenrpmfjfpgsk";
-        let num = get_first_and_last_digit_include_words(s);
-        assert_eq!(num, 67);
+        let num = get_first_and_last_digit_include_words(s, &automaton);
+        assert_eq!(num, Some(67));
 
-        let calibration_strs = vec![
+        let calibration_strs = [
             "two1nine",
             "eightwothree",
             "abcone2threexyz",
@@ -344,12 +652,12 @@ mod tests {
             "zoneight234",
             "7pqrstsixteen",
         ];
-        let calibration_nums = vec![29, 83, 13, 24, 42, 14, 76];
+        let calibration_nums = [29, 83, 13, 24, 42, 14, 76];
 
         for i in 0..calibration_strs.len() {
             assert_eq!(
-                get_first_and_last_digit_include_words(calibration_strs[i]),
-                calibration_nums[i]
+                get_first_and_last_digit_include_words(calibration_strs[i], &automaton),
+                Some(calibration_nums[i])
             );
         }
     }
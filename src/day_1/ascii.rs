@@ -0,0 +1,165 @@
+use super::DigitVocabulary;
+
+const NO_CHILD: u32 = 0;
+
+struct ByteNode {
+    children: [u32; 128],
+    value: Option<u32>,
+}
+
+impl ByteNode {
+    fn new() -> Self {
+        Self {
+            children: [NO_CHILD; 128],
+            value: None,
+        }
+    }
+}
+
+/// Byte trie over the ASCII words of a vocabulary, for lines known to be
+/// ASCII. Children are indexed directly by byte, and nothing is allocated
+/// while matching.
+pub struct AsciiMatcher {
+    nodes: Vec<ByteNode>,
+    // bytes that can begin a match, used to skip ahead cheaply
+    starts: [bool; 128],
+}
+
+impl AsciiMatcher {
+    /// Numerals of `vocabulary` plus every ASCII word of `vocabulary`. Words with
+    /// other characters can never occur in an ASCII line and are left out.
+    pub fn new(vocabulary: &DigitVocabulary) -> Self {
+        let mut matcher = Self {
+            nodes: vec![ByteNode::new()],
+            starts: [false; 128],
+        };
+        for digit in vocabulary.numerals() {
+            matcher.insert(digit.to_string().as_bytes(), digit);
+        }
+        for (word, value) in vocabulary.words() {
+            if word.is_ascii() {
+                matcher.insert(word.as_bytes(), value);
+            }
+        }
+        matcher
+    }
+
+    fn insert(&mut self, word: &[u8], value: u32) {
+        let Some(&first) = word.first() else {
+            return;
+        };
+        self.starts[first as usize] = true;
+        let mut node = 0;
+        for &byte in word {
+            let next = self.nodes[node].children[byte as usize];
+            node = if next == NO_CHILD {
+                self.nodes.push(ByteNode::new());
+                let child = (self.nodes.len() - 1) as u32;
+                self.nodes[node].children[byte as usize] = child;
+                child as usize
+            } else {
+                next as usize
+            };
+        }
+        self.nodes[node].value = Some(value);
+    }
+
+    /// Values of the words starting at `start`, shortest first.
+    fn matches_at<'a>(&'a self, line: &'a [u8], start: usize) -> impl Iterator<Item = u32> + 'a {
+        let mut node = 0;
+        line[start..]
+            .iter()
+            .map_while(move |&byte| {
+                let next = *self.nodes[node].children.get(byte as usize)?;
+                if next == NO_CHILD {
+                    return None;
+                }
+                node = next as usize;
+                Some(self.nodes[node].value)
+            })
+            .flatten()
+    }
+
+    fn is_start(&self, byte: u8) -> bool {
+        self.starts.get(byte as usize).copied().unwrap_or(false)
+    }
+
+    /// Word starting furthest left, the shortest one if several do.
+    pub fn first(&self, line: &[u8]) -> Option<u32> {
+        let mut start = 0;
+        while let Some(offset) = line[start..].iter().position(|&b| self.is_start(b)) {
+            start += offset;
+            if let Some(value) = self.matches_at(line, start).next() {
+                return Some(value);
+            }
+            start += 1;
+        }
+        None
+    }
+
+    /// Word starting furthest right, the longest one if several do.
+    pub fn last(&self, line: &[u8]) -> Option<u32> {
+        let mut end = line.len();
+        while let Some(start) = line[..end].iter().rposition(|&b| self.is_start(b)) {
+            if let Some(value) = self.matches_at(line, start).last() {
+                return Some(value);
+            }
+            end = start;
+        }
+        None
+    }
+
+    /// First and last digit as a two digit number.
+    pub fn calibration_value(&self, line: &[u8]) -> Option<u32> {
+        Some(self.first(line)? * 10 + self.last(line)?)
+    }
+}
+
+/// First and last numeral of an ASCII line as a two digit number.
+pub fn first_last_numerals(line: &[u8]) -> Option<u32> {
+    let first = line.iter().find(|b| b.is_ascii_digit())?;
+    let last = line.iter().rfind(|b| b.is_ascii_digit())?;
+    Some(u32::from(first - b'0') * 10 + u32::from(last - b'0'))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{CalibrationLine, Language};
+    use super::*;
+
+    #[test]
+    fn ascii_numerals() {
+        assert_eq!(first_last_numerals(b"a1b2c3"), Some(13));
+        assert_eq!(first_last_numerals(b"treb7uchet"), Some(77));
+        assert_eq!(first_last_numerals(b"abc"), None);
+        assert_eq!(first_last_numerals(b""), None);
+    }
+
+    #[test]
+    fn ascii_matcher_agrees_with_automaton() {
+        for language in Language::ALL {
+            let vocabulary = DigitVocabulary::builtin(language);
+            let matcher = AsciiMatcher::new(&vocabulary);
+            for line in [
+                "two1nine",
+                "eightwothree",
+                "xtwone3four",
+                "7pqrstsixteen",
+                "oneight",
+                "zweiundvierzig",
+                "dixneuf",
+                "jedenadva",
+                "nothing here",
+                "",
+            ] {
+                assert_eq!(
+                    matcher.calibration_value(line.as_bytes()),
+                    CalibrationLine::decode_with(line, &vocabulary).calibration_value(),
+                    "{:?} in {:?}",
+                    line,
+                    language
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use super::vocabulary::DigitVocabulary;
+use crate::trie::AhoCorasick;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenKind {
+    /// A numeral such as `7`.
+    Numeral,
+    /// A spelled-out word such as `seven`.
+    Word,
+}
+
+/// A digit recognised in a calibration line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DigitToken {
+    pub value: u32,
+    /// Byte range of the token in the line.
+    pub span: Range<usize>,
+    pub kind: TokenKind,
+    /// Whether the token shares characters with another token, like `one`
+    /// and `eight` in `oneight`.
+    pub overlaps: bool,
+}
+
+/// Every digit token of a calibration line, ordered by start position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationLine {
+    tokens: Vec<DigitToken>,
+}
+
+impl CalibrationLine {
+    pub fn decode(line: &str, automaton: &AhoCorasick<u32>) -> Self {
+        let mut tokens: Vec<DigitToken> = automaton
+            .find_all(line)
+            .into_iter()
+            .map(|m| DigitToken {
+                value: *m.value,
+                span: m.start..m.end,
+                kind: if m.as_str(line).chars().all(|ch| ch.is_ascii_digit()) {
+                    TokenKind::Numeral
+                } else {
+                    TokenKind::Word
+                },
+                overlaps: false,
+            })
+            .collect();
+        tokens.sort_by_key(|token| (token.span.start, token.span.end));
+
+        // a token overlaps an earlier one when one of them ends past its
+        // start, and a later one when the next token starts before its end
+        let mut furthest_end = 0;
+        for i in 0..tokens.len() {
+            let span = tokens[i].span.clone();
+            let overlaps_earlier = i > 0 && furthest_end > span.start;
+            let overlaps_later = tokens
+                .get(i + 1)
+                .is_some_and(|next| next.span.start < span.end);
+            tokens[i].overlaps = overlaps_earlier || overlaps_later;
+            furthest_end = furthest_end.max(span.end);
+        }
+
+        Self { tokens }
+    }
+
+    /// Decodes a single line, building the automaton for `vocabulary` on the
+    /// spot. Prefer `decode` when decoding many lines.
+    pub fn decode_with(line: &str, vocabulary: &DigitVocabulary) -> Self {
+        let trie = vocabulary.trie();
+        Self::decode(line, &trie.automaton())
+    }
+
+    pub fn tokens(&self) -> &[DigitToken] {
+        &self.tokens
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&DigitToken> {
+        self.tokens.first()
+    }
+
+    /// Token starting furthest right.
+    pub fn last(&self) -> Option<&DigitToken> {
+        self.tokens.last()
+    }
+
+    /// Numeral tokens only. `0` is one of them when decoding with
+    /// `DigitVocabulary::numerals_only`, as part 1 does.
+    pub fn numerals(&self) -> impl Iterator<Item = &DigitToken> {
+        self.tokens
+            .iter()
+            .filter(|token| token.kind == TokenKind::Numeral)
+    }
+
+    /// First digit times ten plus last digit.
+    pub fn calibration_value(&self) -> Option<u32> {
+        Some(self.first()?.value * 10 + self.last()?.value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_reports_every_token() {
+        let line = CalibrationLine::decode_with("xtwone3four", &DigitVocabulary::default());
+        let tokens: Vec<(u32, Range<usize>, TokenKind, bool)> = line
+            .tokens()
+            .iter()
+            .map(|t| (t.value, t.span.clone(), t.kind, t.overlaps))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (2, 1..4, TokenKind::Word, true),
+                (1, 3..6, TokenKind::Word, true),
+                (3, 6..7, TokenKind::Numeral, false),
+                (4, 7..11, TokenKind::Word, false),
+            ]
+        );
+        assert_eq!(line.calibration_value(), Some(24));
+        assert_eq!(line.numerals().count(), 1);
+    }
+
+    #[test]
+    fn decode_empty_line() {
+        let line = CalibrationLine::decode_with("", &DigitVocabulary::default());
+        assert!(line.is_empty());
+        assert_eq!(line.calibration_value(), None);
+    }
+
+    #[test]
+    fn decode_overlap_with_non_adjacent_token() {
+        let mut vocabulary = DigitVocabulary::empty();
+        vocabulary.insert("abcde", 1).unwrap();
+        vocabulary.insert("b", 2).unwrap();
+        vocabulary.insert("d", 3).unwrap();
+        let line = CalibrationLine::decode_with("abcdef", &vocabulary);
+        assert!(line.tokens().iter().all(|t| t.overlaps));
+    }
+}
@@ -0,0 +1,88 @@
+use super::calibration::CalibrationLine;
+use crate::error::AocError;
+
+/// Turns the digit tokens of a line into its calibration value.
+pub trait CalibrationRule {
+    /// Value of `line`, `None` when the line has no digits to work with.
+    /// Arithmetic is done in `u64` and fails rather than overflowing.
+    fn value(&self, line: &CalibrationLine) -> Result<Option<u64>, AocError>;
+}
+
+fn overflow(rule: &str) -> AocError {
+    AocError::Validation(format!("{} value does not fit in u64", rule))
+}
+
+/// First digit times ten plus last digit, the puzzle's own rule.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FirstLast;
+
+impl CalibrationRule for FirstLast {
+    fn value(&self, line: &CalibrationLine) -> Result<Option<u64>, AocError> {
+        Ok(line.calibration_value().map(u64::from))
+    }
+}
+
+/// Every digit of the line concatenated, `1two3` reads as 123.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Concatenated;
+
+impl CalibrationRule for Concatenated {
+    fn value(&self, line: &CalibrationLine) -> Result<Option<u64>, AocError> {
+        if line.is_empty() {
+            return Ok(None);
+        }
+        line.tokens()
+            .iter()
+            .try_fold(0u64, |acc, token| {
+                acc.checked_mul(10)?.checked_add(token.value.into())
+            })
+            .map(Some)
+            .ok_or_else(|| overflow("concatenated"))
+    }
+}
+
+/// Sum of every digit of the line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DigitSum;
+
+impl CalibrationRule for DigitSum {
+    fn value(&self, line: &CalibrationLine) -> Result<Option<u64>, AocError> {
+        if line.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(line.tokens().iter().map(|t| u64::from(t.value)).sum()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day_1::DigitVocabulary;
+
+    fn decode(line: &str) -> CalibrationLine {
+        CalibrationLine::decode_with(line, &DigitVocabulary::default())
+    }
+
+    #[test]
+    fn builtin_rules() {
+        let line = decode("xtwone3four");
+        assert_eq!(FirstLast.value(&line).unwrap(), Some(24));
+        assert_eq!(Concatenated.value(&line).unwrap(), Some(2134));
+        assert_eq!(DigitSum.value(&line).unwrap(), Some(10));
+
+        let empty = decode("abc");
+        assert_eq!(FirstLast.value(&empty).unwrap(), None);
+        assert_eq!(Concatenated.value(&empty).unwrap(), None);
+        assert_eq!(DigitSum.value(&empty).unwrap(), None);
+    }
+
+    #[test]
+    fn concatenated_overflow() {
+        let line = decode(&"9".repeat(20));
+        assert!(matches!(
+            Concatenated.value(&line),
+            Err(AocError::Validation(_))
+        ));
+        assert_eq!(DigitSum.value(&line).unwrap(), Some(180));
+    }
+}
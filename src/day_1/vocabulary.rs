@@ -0,0 +1,279 @@
+use crate::error::AocError;
+use crate::trie::Trie;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Languages with a built-in digit vocabulary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+    Czech,
+}
+
+impl Language {
+    pub const ALL: [Language; 5] = [
+        Language::English,
+        Language::German,
+        Language::French,
+        Language::Spanish,
+        Language::Czech,
+    ];
+
+    /// Spelled-out digits one to nine. Some languages have more than one
+    /// form for a digit.
+    fn words(self) -> &'static [(&'static str, u32)] {
+        match self {
+            Language::English => &[
+                ("one", 1),
+                ("two", 2),
+                ("three", 3),
+                ("four", 4),
+                ("five", 5),
+                ("six", 6),
+                ("seven", 7),
+                ("eight", 8),
+                ("nine", 9),
+            ],
+            Language::German => &[
+                ("eins", 1),
+                ("zwei", 2),
+                ("drei", 3),
+                ("vier", 4),
+                ("fünf", 5),
+                ("sechs", 6),
+                ("sieben", 7),
+                ("acht", 8),
+                ("neun", 9),
+            ],
+            Language::French => &[
+                ("un", 1),
+                ("deux", 2),
+                ("trois", 3),
+                ("quatre", 4),
+                ("cinq", 5),
+                ("six", 6),
+                ("sept", 7),
+                ("huit", 8),
+                ("neuf", 9),
+            ],
+            Language::Spanish => &[
+                ("uno", 1),
+                ("dos", 2),
+                ("tres", 3),
+                ("cuatro", 4),
+                ("cinco", 5),
+                ("seis", 6),
+                ("siete", 7),
+                ("ocho", 8),
+                ("nueve", 9),
+            ],
+            Language::Czech => &[
+                ("jeden", 1),
+                ("jedna", 1),
+                ("jedno", 1),
+                ("dva", 2),
+                ("dvě", 2),
+                ("tři", 3),
+                ("čtyři", 4),
+                ("pět", 5),
+                ("šest", 6),
+                ("sedm", 7),
+                ("osm", 8),
+                ("devět", 9),
+            ],
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = AocError;
+
+    /// Accepts the English name of the language or its ISO 639-1 code.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(Language::English),
+            "de" | "german" => Ok(Language::German),
+            "fr" | "french" => Ok(Language::French),
+            "es" | "spanish" => Ok(Language::Spanish),
+            "cs" | "czech" => Ok(Language::Czech),
+            _ => Err(AocError::parse_in(s, s, "unknown language")),
+        }
+    }
+}
+
+/// Spelled-out words recognised as digits by the calibration decoder, on top
+/// of the numerals `1` to `9`, or `0` to `9` for part 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigitVocabulary {
+    words: Vec<(String, u32)>,
+    zero: bool,
+}
+
+impl Default for DigitVocabulary {
+    fn default() -> Self {
+        DigitVocabulary::builtin(Language::English)
+    }
+}
+
+impl DigitVocabulary {
+    /// Vocabulary without any words, only numerals are recognised.
+    pub fn empty() -> Self {
+        DigitVocabulary {
+            words: vec![],
+            zero: false,
+        }
+    }
+
+    /// The numerals `0` to `9` and no words, the digits part 1 reads.
+    pub fn numerals_only() -> Self {
+        DigitVocabulary {
+            words: vec![],
+            zero: true,
+        }
+    }
+
+    pub fn builtin(language: Language) -> Self {
+        DigitVocabulary {
+            words: language
+                .words()
+                .iter()
+                .map(|&(word, value)| (word.to_string(), value))
+                .collect(),
+            zero: false,
+        }
+    }
+
+    /// Parses a mapping with one `word = digit` pair per line. Blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn from_mapping(mapping: &str) -> Result<Self, AocError> {
+        let mut vocabulary = DigitVocabulary::empty();
+        for (i, line) in mapping.lines().enumerate() {
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let (word, value) = entry
+                .split_once('=')
+                .ok_or_else(|| AocError::parse_in(line, entry, "expected `word = digit`"))
+                .map_err(|err| err.at_line(i + 1))?;
+            let (word, value) = (word.trim(), value.trim());
+            if word.is_empty() {
+                return Err(AocError::parse_in(line, entry, "missing word").at_line(i + 1));
+            }
+            let value = value
+                .parse::<u32>()
+                .map_err(|_| AocError::parse_in(line, value, "invalid digit").at_line(i + 1))?;
+            vocabulary
+                .insert(word, value)
+                .map_err(|_| AocError::parse_in(line, entry, "digit must be 1-9").at_line(i + 1))?;
+        }
+        Ok(vocabulary)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, AocError> {
+        Self::from_mapping(&fs::read_to_string(path)?)
+    }
+
+    /// Adds a word for `value`, which must be a digit from 1 to 9. An empty
+    /// word would match everywhere, so it is rejected.
+    pub fn insert(&mut self, word: &str, value: u32) -> Result<(), AocError> {
+        if word.is_empty() {
+            return Err(AocError::Validation(format!(
+                "empty word for digit {}",
+                value
+            )));
+        }
+        if !(1..=9).contains(&value) {
+            return Err(AocError::Validation(format!(
+                "`{}` maps to {}, expected a digit from 1 to 9",
+                word, value
+            )));
+        }
+        self.words.push((word.to_string(), value));
+        Ok(())
+    }
+
+    /// Adds the words of another vocabulary, e.g. to accept several
+    /// languages at once.
+    pub fn extend(&mut self, other: &DigitVocabulary) {
+        self.words.extend(other.words.iter().cloned());
+        self.zero |= other.zero;
+    }
+
+    /// Values of the numerals recognised.
+    pub(super) fn numerals(&self) -> RangeInclusive<u32> {
+        if self.zero {
+            0..=9
+        } else {
+            1..=9
+        }
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.words
+            .iter()
+            .map(|(word, value)| (word.as_str(), *value))
+    }
+
+    /// Trie mapping the numerals and every word to its digit.
+    pub fn trie(&self) -> Trie<u32> {
+        let numerals = self.numerals().map(|value| (value.to_string(), value));
+        let words = self
+            .words
+            .iter()
+            .map(|(word, value)| (word.clone(), *value));
+        numerals.chain(words).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builtin_vocabularies_cover_all_digits() {
+        for language in Language::ALL {
+            let trie = DigitVocabulary::builtin(language).trie();
+            let mut digits: Vec<u32> = trie.iter().map(|(_, &value)| value).collect();
+            digits.sort();
+            digits.dedup();
+            assert_eq!(digits, (1..=9).collect::<Vec<_>>(), "{:?}", language);
+        }
+    }
+
+    #[test]
+    fn language_from_str() {
+        assert_eq!("de".parse::<Language>().unwrap(), Language::German);
+        assert_eq!("Czech".parse::<Language>().unwrap(), Language::Czech);
+        assert!("klingon".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn vocabulary_from_mapping() {
+        let mapping = "# roman numerals\nI = 1\n\n  V=5\nIX = 9\n";
+        let vocabulary = DigitVocabulary::from_mapping(mapping).unwrap();
+        let words: Vec<(&str, u32)> = vocabulary.words().collect();
+        assert_eq!(words, vec![("I", 1), ("V", 5), ("IX", 9)]);
+    }
+
+    #[test]
+    fn vocabulary_mapping_errors() {
+        let err = DigitVocabulary::from_mapping("one = 1\ntwo 2\n").unwrap_err();
+        assert!(matches!(err, AocError::Parse { line: 2, .. }));
+
+        let err = DigitVocabulary::from_mapping("ten = 10\n").unwrap_err();
+        assert!(matches!(err, AocError::Parse { line: 1, .. }));
+
+        let err = DigitVocabulary::from_mapping("one = x\n").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 7, .. }));
+
+        let mut vocabulary = DigitVocabulary::empty();
+        assert!(vocabulary.insert("", 1).is_err());
+        assert_eq!(vocabulary.words().count(), 0);
+    }
+}
@@ -1,155 +1,260 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufRead};
+use crate::error::AocError;
+use crate::input;
+use crate::parser::LineParser;
+use crate::solution::{Solution, SolutionResult};
+use std::io::BufRead;
 
-#[derive(Debug)]
+mod bag;
+mod cubes;
+mod estimate;
+mod inference;
+mod probability;
+mod stats;
+mod validation;
+
+pub use bag::Bag;
+use cubes::CubeSetBuilder;
+pub use cubes::{Color, CubeSet, OtherColor};
+pub use estimate::{BagEstimate, ColorInterval, Estimator, TotalPrior};
+pub use inference::{best_bag_within_budget, minimal_bag, smallest_bag_admitting};
+pub use probability::{
+    game_log_probability, game_probability, log_likelihood, monte_carlo_probability,
+    most_probable_bag, rank_games, turn_log_probability, Sampling, SplitMix64,
+};
+pub use stats::{ColorStats, GameStats, GameSummary};
+pub use validation::{Validation, Violation};
+
+/// Cubes revealed in one handful.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Turn {
-    cube_counts: HashMap<String, u32>,
+    cubes: CubeSet,
 }
 
 impl Turn {
-    pub fn from_tuples(turn_tuples: Vec<(&str, &str)>) -> Self {
-        let mut cube_counts = HashMap::with_capacity(5);
-        for t in turn_tuples {
-            let k = String::from(t.1);
-            let v =
-                t.0.parse::<u32>()
-                    .expect(format!("parsing failed {}", t.0).as_str());
-            cube_counts.insert(k, v);
+    /// Builds a turn from `(count, colour)` pairs.
+    pub fn from_tuples(turn_tuples: Vec<(&str, &str)>) -> Result<Self, AocError> {
+        let mut cubes = CubeSetBuilder::new();
+        for (count, color) in turn_tuples {
+            // there is no line to point into, so this is not a parse error
+            let count = count
+                .parse::<u32>()
+                .map_err(|_| AocError::Validation(format!("invalid cube count `{}`", count)))?;
+            cubes.insert(Color::new(color), count).map_err(|color| {
+                AocError::Validation(format!("colour `{}` drawn twice in one turn", color))
+            })?;
         }
-        Self { cube_counts }
+        Ok(Self {
+            cubes: cubes.build(),
+        })
     }
 
-    pub fn power_set(&self) -> u32 {
-        let mut total = 1;
-        for val in self.cube_counts.values() {
-            total *= val;
+    /// `<count> <colour>` draws separated by `,`, up to the next `;`.
+    fn parse(parser: &mut LineParser) -> Result<Self, AocError> {
+        let mut cubes = CubeSetBuilder::new();
+        loop {
+            let count = parser.number("cube count")?;
+            let color_text = parser.word("colour")?;
+            cubes
+                .insert(Color::new(color_text), count)
+                .map_err(|_| parser.error(color_text, "colour drawn twice in one turn"))?;
+
+            // a trailing `,` before `;` or the end of the line is tolerated
+            if !parser.eat(",") || matches!(parser.peek(), None | Some(";")) {
+                return Ok(Self {
+                    cubes: cubes.build(),
+                });
+            }
         }
-        total
+    }
+
+    pub fn cubes(&self) -> &CubeSet {
+        &self.cubes
+    }
+
+    /// Product of the counts, `None` when it overflows `u64`.
+    pub fn power_set(&self) -> Option<u64> {
+        self.cubes.power()
+    }
+}
+
+impl From<CubeSet> for Turn {
+    fn from(cubes: CubeSet) -> Self {
+        Self { cubes }
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Game {
     id: u32,
     turns: Vec<Turn>,
 }
 
 impl Game {
-    pub fn from_line(line: &str) -> Self {
-        let colon_idx = line.find(":").expect("Colon found");
-        let game_id_unparsed = &line[..colon_idx];
-
-        let game_id_unparsed = game_id_unparsed
-            .split(" ")
-            .last()
-            .expect(format!("Invalid game id format {}", game_id_unparsed).as_str());
-        let id = game_id_unparsed.parse::<u32>().expect(
-            format!(
-                "Invalid game id format to parse to u32 {}",
-                game_id_unparsed
-            )
-            .as_str(),
-        );
-        let turns_unparsed = &line[colon_idx + 2..];
-        let turns_parsed: Vec<Vec<(&str, &str)>> = turns_unparsed
-            .split("; ")
-            .map(|cubes_str| {
-                cubes_str
-                    .split(", ")
-                    .filter_map(|cube_turn| {
-                        cube_turn
-                            .split_once(' ')
-                            .or_else(|| panic!("cube_turn: {} is invalid", cube_turn))
+    /// Parses `Game <id>: <turn>; <turn>; ...`. Whitespace between tokens
+    /// is free and a trailing `,` or `;` is allowed.
+    pub fn from_line(line: &str) -> Result<Self, AocError> {
+        let mut parser = LineParser::new(line);
+        let id = parser.header("Game")?;
+        let mut turns = vec![];
+        loop {
+            turns.push(Turn::parse(&mut parser)?);
+            if !parser.eat(";") || parser.is_at_end() {
+                break;
+            }
+        }
+        if !parser.is_at_end() {
+            return Err(parser.error_here("expected `,` or `;`"));
+        }
+
+        Ok(Self { id, turns })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Whether every turn fits in the validator. A colour the validator does
+    /// not list has no cubes, so any turn drawing it is impossible.
+    pub fn is_valid(&self, validator_turn: &Turn) -> bool {
+        self.validate(&validator_turn.cubes).is_valid()
+    }
+
+    /// Every draw of the game that a bag holding `bag` could not produce.
+    pub fn validate(&self, bag: &CubeSet) -> Validation {
+        let violations = self
+            .turns
+            .iter()
+            .enumerate()
+            .flat_map(|(i, turn)| {
+                turn.cubes
+                    .iter()
+                    .filter(|(color, observed)| *observed > bag.get(color))
+                    .map(move |(color, observed)| Violation {
+                        turn: i,
+                        limit: bag.get(&color),
+                        color,
+                        observed,
                     })
-                    .collect::<Vec<_>>()
             })
             .collect();
-        let turns: Vec<Turn> = turns_parsed
-            .into_iter()
-            .map(|turn| Turn::from_tuples(turn))
-            .collect();
-
-        Self { id, turns }
+        Validation::new(self.id, violations)
     }
 
-    pub fn is_valid(&self, validator_turn: &Turn) -> bool {
-        for turn in self.turns.iter() {
-            for color in turn.cube_counts.keys() {
-                let validator_count = validator_turn.cube_counts.get(color);
-                if validator_count.is_none() {
-                    panic!("color {} does not exist in validator", color);
-                }
-                let validator_count = validator_count.unwrap();
-                let cube_count = turn.cube_counts.get(color).unwrap(); // assume exists
-                if cube_count <= validator_count {
-                    continue;
-                } else {
-                    return false;
-                }
-            }
-        }
-        true
+    /// Same answer as `validate(cubes).is_valid()` without collecting the
+    /// violations, for the searches that check many bags.
+    pub fn fits_in(&self, cubes: &CubeSet) -> bool {
+        self.turns.iter().all(|turn| turn.cubes.is_subset_of(cubes))
     }
 
+    /// Fewest cubes of each colour that could have produced every turn.
     pub fn take_most_from_turns(&self) -> Turn {
-        let mut most_turn_hm: HashMap<String, u32> = HashMap::new();
-
-        for turn in self.turns.iter() {
-            for color in turn.cube_counts.keys() {
-                let cube_count = turn.cube_counts.get(color).unwrap(); // exists
-                let maybe_curr_most = most_turn_hm.get_mut(color);
-                if let Some(curr_most) = maybe_curr_most {
-                    if *curr_most < *cube_count {
-                        *curr_most = *cube_count;
-                    }
-                } else {
-                    most_turn_hm.insert(color.to_string(), *cube_count);
-                }
-            }
-        }
-
-        Turn {
-            cube_counts: most_turn_hm,
-        }
+        let cubes = self
+            .turns
+            .iter()
+            .fold(CubeSet::new(), |most, turn| most.max(&turn.cubes));
+        Turn { cubes }
     }
 
-    pub fn power_minimum(&self) -> u32 {
+    pub fn power_minimum(&self) -> Result<u64, AocError> {
         let min_turn = self.take_most_from_turns();
-        min_turn.power_set()
+        min_turn
+            .power_set()
+            .ok_or_else(|| AocError::Validation(format!("power of game {} overflows u64", self.id)))
     }
 }
 
-pub fn solution_part_1(filename: &str) -> u32 {
-    let file = File::open(filename).expect("failed to parse file.");
-    let validator_counts = vec![("12", "red"), ("13", "green"), ("14", "blue")];
-    let validator_turn = Turn::from_tuples(validator_counts);
+/// Every game of the input, in order.
+pub fn parse_games<R: BufRead>(reader: R) -> Result<Vec<Game>, AocError> {
+    input::numbered_lines(reader)
+        .map(|line| {
+            let (line_number, ip) = line?;
+            Game::from_line(&ip).map_err(|err| err.at_line(line_number))
+        })
+        .collect()
+}
+
+pub fn part_1_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    part_1_from_reader_with_bag(reader, &Bag::puzzle())
+}
 
-    let lines = io::BufReader::new(file).lines();
+/// Sum of the ids of the games `bag` admits.
+pub fn part_1_from_reader_with_bag<R: BufRead>(reader: R, bag: &Bag) -> Result<u64, AocError> {
+    let admitted = evaluate_bags(reader, std::slice::from_ref(bag))?;
+    Ok(admitted[0].iter().map(|&id| u64::from(id)).sum())
+}
 
-    let mut total: u32 = 0;
-    for line in lines {
-        if let Ok(ip) = line {
-            let game = Game::from_line(ip.as_str());
-            if game.is_valid(&validator_turn) {
-                total += game.id
+/// Checks every game against each of `bags` in a single pass. Returns the
+/// ids of the admitted games for each bag, in the order of `bags`.
+pub fn evaluate_bags<R: BufRead>(reader: R, bags: &[Bag]) -> Result<Vec<Vec<u32>>, AocError> {
+    let mut admitted = vec![vec![]; bags.len()];
+    for line in input::numbered_lines(reader) {
+        let (line_number, ip) = line?;
+        let game = Game::from_line(ip.as_str()).map_err(|err| err.at_line(line_number))?;
+        for (bag, ids) in bags.iter().zip(admitted.iter_mut()) {
+            if bag.admits(&game) {
+                ids.push(game.id);
             }
         }
     }
-    total
+    Ok(admitted)
 }
 
-pub fn solution_part_2(filename: &str) -> u32 {
-    let file = File::open(filename).expect("failed to parse file.");
+pub fn part_2_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    let mut total: u64 = 0;
+    for line in input::numbered_lines(reader) {
+        let (line_number, ip) = line?;
+        let game = Game::from_line(ip.as_str()).map_err(|err| err.at_line(line_number))?;
+        total = total.checked_add(game.power_minimum()?).ok_or_else(|| {
+            AocError::Validation(format!("total overflows u64 at line {}", line_number))
+        })?;
+    }
+    Ok(total)
+}
 
-    let lines = io::BufReader::new(file).lines();
-    let mut total: u32 = 0;
-    for line in lines {
-        if let Ok(ip) = line {
-            let game = Game::from_line(ip.as_str());
-            total += game.power_minimum();
-        }
+pub fn part_1(input: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input.as_bytes())
+}
+
+pub fn part_1_with_bag(input: &str, bag: &Bag) -> Result<u64, AocError> {
+    part_1_from_reader_with_bag(input.as_bytes(), bag)
+}
+
+pub fn part_2(input: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input.as_bytes())
+}
+
+pub fn solution_part_1(filename: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input::open(filename)?)
+}
+
+pub fn solution_part_1_with_bag(filename: &str, bag: &Bag) -> Result<u64, AocError> {
+    part_1_from_reader_with_bag(input::open(filename)?, bag)
+}
+
+pub fn solution_part_2(filename: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input::open(filename)?)
+}
+
+pub struct DayTwo;
+
+impl Solution for DayTwo {
+    fn day(&self) -> u8 {
+        2
+    }
+
+    fn part_one(&self, input: &str) -> SolutionResult {
+        Ok(part_1(input)?.into())
+    }
+
+    fn part_two(&self, input: &str) -> SolutionResult {
+        Ok(part_2(input)?.into())
     }
-    total
 }
 
 #[cfg(test)]
@@ -159,7 +264,7 @@ mod test {
     #[test]
     fn game_parse_line() {
         let line = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let game = Game::from_line(line);
+        let game = Game::from_line(line).unwrap();
         assert_eq!(game.id, 1);
         assert_eq!(game.turns.len(), 3);
     }
@@ -167,31 +272,217 @@ mod test {
     #[test]
     fn game_check_validity() {
         let line = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let game = Game::from_line(line);
+        let game = Game::from_line(line).unwrap();
         let validator_counts = vec![("12", "red"), ("13", "green"), ("14", "blue")];
-        let validator_turn = Turn::from_tuples(validator_counts);
+        let validator_turn = Turn::from_tuples(validator_counts).unwrap();
         let is_valid = game.is_valid(&validator_turn);
         assert!(is_valid)
     }
 
+    #[test]
+    fn game_parse_errors() {
+        let err = Game::from_line("Game 1: 3 blue, x red").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 17, .. }));
+
+        let err = Game::from_line("Game one: 3 blue").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 6, .. }));
+
+        let err = Game::from_line("Game 1 3 blue").err().unwrap();
+        assert!(matches!(err, AocError::Parse { .. }));
+
+        let err = Game::from_line("Game 1: 3blue").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 9, .. }));
+
+        let err = Game::from_line("Game 1: 3 blue; 2 red, 1 blue, 4 red")
+            .err()
+            .unwrap();
+        assert!(matches!(err, AocError::Parse { column: 34, .. }));
+
+        let err = Game::from_line("Game +1: 3 red").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 6, .. }));
+
+        let err = Game::from_line("Game 1: +3 red").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 9, .. }));
+
+        let err = Game::from_line("Game 1: 0 red, 3 red").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 18, .. }));
+
+        let err = Game::from_line("Game 1: 3 blue 4 red").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 16, .. }));
+
+        let err = Game::from_line("Game 1: 3 blue;; 4 red").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 16, .. }));
+
+        let err = Game::from_line("Game 1:").err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 8, .. }));
+    }
+
+    #[test]
+    fn game_parse_tolerates_whitespace() {
+        let canonical = Game::from_line("Game 7: 3 blue, 4 red; 1 red, 2 green").unwrap();
+        for line in [
+            "Game  7 :3 blue ,4 red;1 red,2 green",
+            "Game\t7:\t3 blue,\t4 red;  1 red, 2 green\r",
+            "Game 7: 3 blue, 4 red,; 1 red, 2 green;",
+        ] {
+            let game = Game::from_line(line).unwrap();
+            assert_eq!(game.id, canonical.id, "{:?}", line);
+            assert_eq!(game.turns, canonical.turns, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn game_unknown_color_is_not_valid() {
+        let game = Game::from_line("Game 1: 3 purple").unwrap();
+        let validator_turn = Turn::from_tuples(vec![("12", "red")]).unwrap();
+        assert!(!game.is_valid(&validator_turn));
+
+        let validator_turn = Turn::from_tuples(vec![("3", "purple")]).unwrap();
+        assert!(game.is_valid(&validator_turn));
+
+        assert!(Turn::from_tuples(vec![("0", "red"), ("3", "red")]).is_err());
+    }
+
+    #[test]
+    fn game_validate_lists_violations() {
+        let game = Game::from_line(
+            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 15 blue, 14 red",
+        )
+        .unwrap();
+        let validation = game.validate(Bag::puzzle().cubes());
+        assert!(!validation.is_valid());
+        assert_eq!(validation.game(), 3);
+        let found: Vec<(usize, Color, u32, u32)> = validation
+            .violations()
+            .iter()
+            .map(|v| (v.turn, v.color.clone(), v.observed, v.limit))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, Color::Red, 20, 12),
+                (2, Color::Red, 14, 12),
+                (2, Color::Blue, 15, 14)
+            ]
+        );
+        assert_eq!(
+            validation.to_string(),
+            "game 3 is impossible: turn 1 drew 20 red, the bag holds 12; \
+             turn 3 drew 14 red, the bag holds 12; turn 3 drew 15 blue, the bag holds 14"
+        );
+
+        let validation = game.validate(&minimal_bag(std::slice::from_ref(&game)));
+        assert!(validation.is_valid());
+        assert_eq!(validation.to_string(), "game 3 is possible");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn game_serde_round_trip() {
+        let game = Game::from_line("Game 7: 3 blue, 4 red; 2 teal").unwrap();
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(json, r#"{"id":7,"turns":[{"red":4,"blue":3},{"teal":2}]}"#);
+        let back: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.id(), 7);
+        assert_eq!(back.turns(), game.turns());
+
+        let bag: Bag = serde_json::from_str(r#"{"name":"b","cubes":{"red":0,"blue":2}}"#).unwrap();
+        assert_eq!(bag.name(), "b");
+        assert_eq!(bag.cubes(), Bag::from_spec("blue=2").unwrap().cubes());
+    }
+
+    #[test]
+    fn game_minimum_set() {
+        let game =
+            Game::from_line("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green").unwrap();
+        let expected: CubeSet = [(Color::Red, 20), (Color::Green, 13), (Color::Blue, 6)]
+            .into_iter()
+            .collect();
+        assert_eq!(game.take_most_from_turns().cubes(), &expected);
+        assert_eq!(game.turns()[1].cubes().get(&Color::Blue), 5);
+    }
+
+    pub(super) const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+";
+
+    pub(super) fn rgb(red: u32, green: u32, blue: u32) -> CubeSet {
+        [
+            (Color::Red, red),
+            (Color::Green, green),
+            (Color::Blue, blue),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    /// Games of three handfuls of `size` cubes drawn from `bag`.
+    pub(super) fn simulate(
+        bag: &CubeSet,
+        games: u32,
+        size: usize,
+        sampling: Sampling,
+    ) -> Vec<Game> {
+        let mut cubes = probability::one_per_cube(bag);
+        let mut rng = SplitMix64::new(2023);
+        (1..=games)
+            .map(|id| Game {
+                id,
+                turns: (0..3)
+                    .map(|_| Turn::from(probability::draw(&mut cubes, size, sampling, &mut rng)))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn day_2_example() {
+        assert_eq!(part_1(EXAMPLE).unwrap(), 8);
+        assert_eq!(part_2(EXAMPLE).unwrap(), 2286);
+    }
+
+    #[test]
+    fn day_2_several_bags() {
+        let bags = [
+            Bag::puzzle(),
+            Bag::from_spec("red=20,green=13,blue=15").unwrap(),
+            Bag::from_spec("red=1,green=1,blue=1").unwrap(),
+        ];
+        let admitted = evaluate_bags(EXAMPLE.as_bytes(), &bags).unwrap();
+        assert_eq!(admitted, vec![vec![1, 2, 5], vec![1, 2, 3, 4, 5], vec![]]);
+        assert_eq!(part_1_with_bag(EXAMPLE, &bags[1]).unwrap(), 15);
+
+        let input = "Game 4294967295: 1 red\n".repeat(2);
+        assert_eq!(part_1(&input).unwrap(), 2 * u64::from(u32::MAX));
+    }
+
     #[test]
     fn day_2_part_1() {
         let filename = "data/day_2.txt";
-        let solution = solution_part_1(filename);
+        let solution = solution_part_1(filename).unwrap();
         println!("Solution part 1: {}", solution);
     }
 
     #[test]
     fn game_power() {
         let line = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
-        let game = Game::from_line(line);
-        assert_eq!(game.power_minimum(), 48);
+        let game = Game::from_line(line).unwrap();
+        assert_eq!(game.power_minimum().unwrap(), 48);
+
+        let input = "Game 1: 4000000000 red, 4000000000 green, 4000000000 blue\n";
+        assert!(matches!(part_2(input), Err(AocError::Validation(_))));
+        let input = "Game 1: 4000000000 red, 4000000000 green\n".repeat(2);
+        assert!(matches!(part_2(&input), Err(AocError::Validation(_))));
     }
 
     #[test]
     fn day_2_part_2() {
         let filename = "data/day_2.txt";
-        let solution = solution_part_2(filename);
+        let solution = solution_part_2(filename).unwrap();
         println!("Solution part 2: {}", solution);
     }
 }
@@ -0,0 +1,261 @@
+use super::{Color, CubeSet, CubeSetBuilder, Game};
+use crate::error::AocError;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// A named set of cube limits a game is checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bag {
+    name: String,
+    cubes: CubeSet,
+}
+
+impl Bag {
+    pub fn new(name: impl Into<String>, cubes: CubeSet) -> Self {
+        Self {
+            name: name.into(),
+            cubes,
+        }
+    }
+
+    /// The bag from the puzzle: 12 red, 13 green and 14 blue cubes.
+    pub fn puzzle() -> Self {
+        let cubes = [(Color::Red, 12), (Color::Green, 13), (Color::Blue, 14)]
+            .into_iter()
+            .collect();
+        Self::new("puzzle", cubes)
+    }
+
+    /// Parses `red=12,green=13,blue=14`, the bag is named after the spec.
+    pub fn from_spec(spec: &str) -> Result<Self, AocError> {
+        let mut cubes = CubeSetBuilder::new();
+        parse_entries(spec, spec, &mut cubes)?;
+        Ok(Self::new(spec.trim(), cubes.build()))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn cubes(&self) -> &CubeSet {
+        &self.cubes
+    }
+
+    /// Whether every turn of `game` could have been drawn from this bag.
+    pub fn admits(&self, game: &Game) -> bool {
+        game.fits_in(&self.cubes)
+    }
+
+    /// Parses one or more bags.
+    ///
+    /// The bag list format has lines of `colour = count` entries, several
+    /// to a line when separated by commas, a `[name]` line starting a new
+    /// bag and `#` starting a comment. Bags before the first `[name]` line
+    /// are called `bag N`.
+    ///
+    /// With the `serde` feature, JSON is read as well: either an object of
+    /// counts, for a single bag, or an array of bags as serialized by
+    /// `Bag`, `[{"name": "puzzle", "cubes": {"red": 12}}]`.
+    pub fn parse_list(config: &str) -> Result<Vec<Bag>, AocError> {
+        let trimmed = config.trim_start();
+        let is_json = trimmed.starts_with('{')
+            || (trimmed.starts_with('[') && trimmed[1..].trim_start().starts_with(['{', ']']));
+        if is_json {
+            parse_json(config)
+        } else {
+            parse_sections(config)
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Vec<Bag>, AocError> {
+        Self::parse_list(&fs::read_to_string(path)?)
+    }
+}
+
+impl Default for Bag {
+    fn default() -> Self {
+        Self::puzzle()
+    }
+}
+
+impl fmt::Display for Bag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.cubes)
+    }
+}
+
+/// Adds the comma separated `colour = count` entries of `text`, a subslice
+/// of `line`, to `cubes`.
+fn parse_entries(line: &str, text: &str, cubes: &mut CubeSetBuilder) -> Result<(), AocError> {
+    for entry in text.split(',') {
+        let trimmed = entry.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let (color, count) = trimmed
+            .split_once('=')
+            .ok_or_else(|| AocError::parse_in(line, trimmed, "expected `colour = count`"))?;
+        let (color, count) = (color.trim(), count.trim());
+        if color.is_empty() {
+            return Err(AocError::parse_in(line, trimmed, "missing colour"));
+        }
+        let count = count
+            .parse::<u32>()
+            .map_err(|_| AocError::parse_in(line, count, "invalid cube count"))?;
+        cubes.insert(Color::new(color), count).map_err(|color| {
+            AocError::parse_in(line, trimmed, format!("colour `{}` listed twice", color))
+        })?;
+    }
+    Ok(())
+}
+
+fn parse_sections(config: &str) -> Result<Vec<Bag>, AocError> {
+    let mut bags: Vec<Bag> = vec![];
+    let mut current: Option<(String, CubeSetBuilder)> = None;
+    for (i, line) in config.lines().enumerate() {
+        let content = line.split('#').next().unwrap_or_default().trim();
+        if content.is_empty() {
+            continue;
+        }
+        if let Some(section) = content.strip_prefix('[') {
+            let name = section
+                .strip_suffix(']')
+                .ok_or_else(|| AocError::parse_in(line, content, "expected `[name]`"))
+                .map_err(|err| err.at_line(i + 1))?;
+            bags.extend(
+                current
+                    .take()
+                    .map(|(name, cubes)| Bag::new(name, cubes.build())),
+            );
+            current = Some((name.trim().to_string(), CubeSetBuilder::new()));
+            continue;
+        }
+        let (_, cubes) = current
+            .get_or_insert_with(|| (format!("bag {}", bags.len() + 1), CubeSetBuilder::new()));
+        parse_entries(line, content, cubes).map_err(|err| err.at_line(i + 1))?;
+    }
+    bags.extend(current.map(|(name, cubes)| Bag::new(name, cubes.build())));
+    Ok(bags)
+}
+
+#[cfg(feature = "serde")]
+fn parse_json(config: &str) -> Result<Vec<Bag>, AocError> {
+    let parsed = if config.trim_start().starts_with('{') {
+        serde_json::from_str(config).map(|cubes| vec![Bag::new("bag 1", cubes)])
+    } else {
+        serde_json::from_str(config)
+    };
+    parsed.map_err(|err| {
+        // serde_json counts columns in bytes and appends the position to
+        // its message, the position goes into the error fields instead
+        let text = config
+            .lines()
+            .nth(err.line().saturating_sub(1))
+            .unwrap_or("");
+        let mut offset = err.column().saturating_sub(1).min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let message = err.to_string();
+        let suffix = format!(" at line {} column {}", err.line(), err.column());
+        AocError::Parse {
+            line: err.line(),
+            column: text[..offset].chars().count() + 1,
+            text: text.trim().to_string(),
+            message: message
+                .strip_suffix(&suffix)
+                .unwrap_or(&message)
+                .to_string(),
+        }
+    })
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_json(_: &str) -> Result<Vec<Bag>, AocError> {
+    Err(AocError::Validation(
+        "reading bags from JSON needs the `serde` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::rgb;
+    use super::*;
+
+    #[test]
+    fn bag_from_spec() {
+        let bag = Bag::from_spec("red=12, green = 13,blue=14").unwrap();
+        assert_eq!(bag.cubes(), Bag::puzzle().cubes());
+
+        let err = Bag::from_spec("red=12,green=x").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 14, .. }));
+        let err = Bag::from_spec("red=1,red=2").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 7, .. }));
+        let err = Bag::from_spec("red=0,red=5").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 7, .. }));
+        assert!(Bag::from_spec("red").is_err());
+    }
+
+    #[test]
+    fn bag_list_sections() {
+        let config = "\
+# the puzzle bag
+red = 12
+green = 13, blue = 14
+
+[small]
+red = 1
+green = 1
+blue = 1
+";
+        let bags = Bag::parse_list(config).unwrap();
+        assert_eq!(bags.len(), 2);
+        assert_eq!(bags[0], Bag::new("bag 1", rgb(12, 13, 14)));
+        assert_eq!(bags[1], Bag::new("small", rgb(1, 1, 1)));
+
+        assert!(Bag::parse_list("red = \"12\"\n").is_err());
+        let err = Bag::parse_list("[big]\nred = 1\nblue 3\n").unwrap_err();
+        assert!(matches!(
+            err,
+            AocError::Parse {
+                line: 3,
+                column: 1,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bag_list_json() {
+        let single = Bag::parse_list(r#"{"red": 12, "green": 13, "blue": 14}"#).unwrap();
+        assert_eq!(single, vec![Bag::new("bag 1", rgb(12, 13, 14))]);
+
+        let list = Bag::parse_list(
+            r#"[
+                {"name": "puzzle", "cubes": {"red": 12, "green": 13, "blue": 14}},
+                {"name": "tiny", "cubes": {"red\u0020": 1}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(list[0], Bag::puzzle());
+        assert_eq!(list[1].cubes().get(&Color::new("red ")), 1);
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(Bag::parse_list(&json).unwrap(), list);
+
+        let err = Bag::parse_list("{\n  \"red\": 12,\n  \"blue\": -1\n}").unwrap_err();
+        assert!(matches!(err, AocError::Parse { line: 3, .. }), "{}", err);
+        assert!(Bag::parse_list(r#"{"red": 0, "red": 5}"#).is_err());
+        assert!(Bag::parse_list(r#"{"red": 1"#).is_err());
+        assert!(Bag::parse_list(r#"{"red": 1} x"#).is_err());
+    }
+
+    #[cfg(not(feature = "serde"))]
+    #[test]
+    fn bag_list_json_needs_serde() {
+        let err = Bag::parse_list(r#"{"red": 12}"#).unwrap_err();
+        assert!(matches!(err, AocError::Validation(_)));
+    }
+}
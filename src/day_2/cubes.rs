@@ -0,0 +1,420 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub};
+
+/// Colour of a cube. The puzzle's three colours need no allocation, any
+/// other name is kept as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Color {
+    Red,
+    Green,
+    Blue,
+    Other(OtherColor),
+}
+
+/// Name of a colour other than red, green and blue. Only `Color::new` makes
+/// one, so a puzzle colour is never spelled as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OtherColor(Box<str>);
+
+impl OtherColor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Color {
+    pub const KNOWN: [Color; 3] = [Color::Red, Color::Green, Color::Blue];
+
+    pub fn new(name: &str) -> Self {
+        match name {
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "blue" => Color::Blue,
+            other => Color::Other(OtherColor(other.into())),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Blue => "blue",
+            Color::Other(name) => name.as_str(),
+        }
+    }
+
+    fn known_index(&self) -> Option<usize> {
+        match self {
+            Color::Red => Some(0),
+            Color::Green => Some(1),
+            Color::Blue => Some(2),
+            Color::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Number of cubes of each colour. A colour that is not listed counts as
+/// zero, so two sets are equal when every colour has the same count.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CubeSet {
+    known: [u32; 3],
+    // sorted by name, never holding a zero count
+    others: Vec<(Box<str>, u32)>,
+}
+
+impl CubeSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, color: &Color) -> u32 {
+        match color.known_index() {
+            Some(i) => self.known[i],
+            None => self.get_other(color.name()),
+        }
+    }
+
+    pub fn set(&mut self, color: Color, count: u32) {
+        if let Some(i) = color.known_index() {
+            self.known[i] = count;
+            return;
+        }
+        let Color::Other(OtherColor(name)) = color else {
+            unreachable!("known colours are handled above")
+        };
+        match (self.other_index(&name), count) {
+            (Ok(i), 0) => {
+                self.others.remove(i);
+            }
+            (Ok(i), _) => self.others[i].1 = count,
+            (Err(_), 0) => {}
+            (Err(i), _) => self.others.insert(i, (name, count)),
+        }
+    }
+
+    fn other_index(&self, name: &str) -> Result<usize, usize> {
+        self.others
+            .binary_search_by(|(other, _)| other.as_ref().cmp(name))
+    }
+
+    /// Colours with a non-zero count, red, green and blue first.
+    pub fn iter(&self) -> impl Iterator<Item = (Color, u32)> + '_ {
+        Color::KNOWN
+            .into_iter()
+            .zip(self.known)
+            .chain(
+                self.others
+                    .iter()
+                    .map(|(name, count)| (Color::Other(OtherColor(name.clone())), *count)),
+            )
+            .filter(|&(_, count)| count > 0)
+    }
+
+    /// Total number of cubes.
+    pub fn total(&self) -> u64 {
+        self.iter().map(|(_, count)| u64::from(count)).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Applies `f` to the counts of every colour present in either set.
+    fn zip_with(&self, other: &CubeSet, f: impl Fn(u32, u32) -> u32) -> CubeSet {
+        let mut known = [0; 3];
+        for (i, count) in known.iter_mut().enumerate() {
+            *count = f(self.known[i], other.known[i]);
+        }
+
+        let mut others = Vec::with_capacity(self.others.len().max(other.others.len()));
+        let (mut left, mut right) = (
+            self.others.iter().peekable(),
+            other.others.iter().peekable(),
+        );
+        loop {
+            let (name, count) = match (left.peek(), right.peek()) {
+                (None, None) => break,
+                (Some((name, a)), None) => {
+                    left.next();
+                    (name, f(*a, 0))
+                }
+                (None, Some((name, b))) => {
+                    right.next();
+                    (name, f(0, *b))
+                }
+                (Some((l, a)), Some((r, b))) => match l.cmp(r) {
+                    Ordering::Less => {
+                        left.next();
+                        (l, f(*a, 0))
+                    }
+                    Ordering::Greater => {
+                        right.next();
+                        (r, f(0, *b))
+                    }
+                    Ordering::Equal => {
+                        left.next();
+                        right.next();
+                        (l, f(*a, *b))
+                    }
+                },
+            };
+            if count > 0 {
+                others.push((name.clone(), count));
+            }
+        }
+
+        CubeSet { known, others }
+    }
+
+    /// Component-wise maximum.
+    pub fn max(&self, other: &CubeSet) -> CubeSet {
+        self.zip_with(other, u32::max)
+    }
+
+    /// Component-wise minimum.
+    pub fn min(&self, other: &CubeSet) -> CubeSet {
+        self.zip_with(other, u32::min)
+    }
+
+    /// Whether no colour has more cubes than in `other`.
+    pub fn is_subset_of(&self, other: &CubeSet) -> bool {
+        self.known.iter().zip(other.known).all(|(&a, b)| a <= b)
+            && self
+                .others
+                .iter()
+                .all(|(name, count)| *count <= other.get_other(name))
+    }
+
+    fn get_other(&self, name: &str) -> u32 {
+        self.other_index(name).map_or(0, |i| self.others[i].1)
+    }
+
+    /// Product of the non-zero counts, 1 for an empty set. `None` when the
+    /// product overflows `u64`.
+    pub fn power(&self) -> Option<u64> {
+        self.iter().try_fold(1u64, |product, (_, count)| {
+            product.checked_mul(u64::from(count))
+        })
+    }
+}
+
+/// Builds a `CubeSet` from counts listed one colour at a time, as in a turn
+/// or a bag, where listing a colour twice is a mistake.
+#[derive(Debug, Default)]
+pub(super) struct CubeSetBuilder {
+    cubes: CubeSet,
+    // a count of zero leaves no trace in `cubes`
+    listed: Vec<Color>,
+}
+
+impl CubeSetBuilder {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the count of `color`, handing the colour back when it was
+    /// already listed.
+    pub(super) fn insert(&mut self, color: Color, count: u32) -> Result<(), Color> {
+        if self.listed.contains(&color) {
+            return Err(color);
+        }
+        self.cubes.set(color.clone(), count);
+        self.listed.push(color);
+        Ok(())
+    }
+
+    pub(super) fn build(self) -> CubeSet {
+        self.cubes
+    }
+}
+
+impl FromIterator<(Color, u32)> for CubeSet {
+    /// Later counts of the same colour replace earlier ones.
+    fn from_iter<I: IntoIterator<Item = (Color, u32)>>(iter: I) -> Self {
+        let mut set = CubeSet::new();
+        for (color, count) in iter {
+            set.set(color, count);
+        }
+        set
+    }
+}
+
+/// Component-wise sum, saturating at `u32::MAX`.
+impl Add for &CubeSet {
+    type Output = CubeSet;
+
+    fn add(self, other: &CubeSet) -> CubeSet {
+        self.zip_with(other, u32::saturating_add)
+    }
+}
+
+/// Component-wise difference, floored at zero.
+impl Sub for &CubeSet {
+    type Output = CubeSet;
+
+    fn sub(self, other: &CubeSet) -> CubeSet {
+        self.zip_with(other, u32::saturating_sub)
+    }
+}
+
+impl fmt::Display for CubeSet {
+    /// `3 red, 4 blue`, the way a turn is written in the input.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (color, count)) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} {}", count, color)?;
+        }
+        Ok(())
+    }
+}
+
+// a colour is its name and a set maps names to counts, so both read the same
+// as in the puzzle input
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Color::new(&name))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CubeSet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CubeSet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CountsVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for CountsVisitor {
+            type Value = CubeSet;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of cube counts")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<CubeSet, A::Error> {
+                let mut cubes = CubeSetBuilder::new();
+                while let Some((color, count)) = map.next_entry::<Color, u32>()? {
+                    cubes.insert(color, count).map_err(|color| {
+                        serde::de::Error::custom(format!("colour `{}` listed twice", color))
+                    })?;
+                }
+                Ok(cubes.build())
+            }
+        }
+
+        deserializer.deserialize_map(CountsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cubes(counts: &[(&str, u32)]) -> CubeSet {
+        counts
+            .iter()
+            .map(|&(name, count)| (Color::new(name), count))
+            .collect()
+    }
+
+    #[test]
+    fn color_new_keeps_puzzle_colours_known() {
+        assert_eq!(Color::new("red"), Color::Red);
+        let Color::Other(purple) = Color::new("purple") else {
+            panic!("purple is not a puzzle colour")
+        };
+        assert_eq!(purple.as_str(), "purple");
+        assert_eq!(cubes(&[("red", 2)]).get(&Color::Red), 2);
+    }
+
+    #[test]
+    fn cube_set_arithmetic() {
+        let a = cubes(&[("red", 3), ("blue", 4), ("purple", 2)]);
+        let b = cubes(&[("red", 1), ("green", 2), ("amber", 5), ("purple", 3)]);
+
+        assert_eq!(
+            a.max(&b),
+            cubes(&[
+                ("red", 3),
+                ("green", 2),
+                ("blue", 4),
+                ("amber", 5),
+                ("purple", 3)
+            ])
+        );
+        assert_eq!(a.min(&b), cubes(&[("red", 1), ("purple", 2)]));
+        assert_eq!(
+            &a + &b,
+            cubes(&[
+                ("red", 4),
+                ("green", 2),
+                ("blue", 4),
+                ("amber", 5),
+                ("purple", 5)
+            ])
+        );
+        assert_eq!(&a - &b, cubes(&[("red", 2), ("blue", 4)]));
+        assert_eq!(a.to_string(), "3 red, 4 blue, 2 purple");
+    }
+
+    #[test]
+    fn cube_set_zero_counts_are_absent() {
+        let mut set = cubes(&[("purple", 2), ("red", 0)]);
+        set.set(Color::new("purple"), 0);
+        assert_eq!(set, CubeSet::new());
+        assert!(set.is_empty());
+        assert_eq!(set.get(&Color::new("purple")), 0);
+        assert_eq!(set.power(), Some(1));
+    }
+
+    #[test]
+    fn cube_set_builder_rejects_listed_colours() {
+        let mut builder = CubeSetBuilder::new();
+        builder.insert(Color::Red, 0).unwrap();
+        builder.insert(Color::new("purple"), 2).unwrap();
+        assert_eq!(builder.insert(Color::new("red"), 3), Err(Color::Red));
+        assert_eq!(builder.build(), cubes(&[("purple", 2)]));
+    }
+
+    #[test]
+    fn cube_set_subset_and_power() {
+        let bag = cubes(&[("red", 12), ("green", 13), ("blue", 14)]);
+        assert!(cubes(&[("red", 12), ("blue", 1)]).is_subset_of(&bag));
+        assert!(!cubes(&[("red", 13)]).is_subset_of(&bag));
+        assert!(!cubes(&[("purple", 1)]).is_subset_of(&bag));
+        assert!(CubeSet::new().is_subset_of(&bag));
+
+        assert_eq!(
+            cubes(&[("red", 4), ("green", 2), ("blue", 6)]).power(),
+            Some(48)
+        );
+        let big = cubes(&[("red", u32::MAX), ("green", u32::MAX), ("blue", 2)]);
+        assert_eq!(big.power(), None);
+        assert_eq!(bag.total(), 39);
+    }
+}
@@ -0,0 +1,562 @@
+// the estimate starts from the minimal bag, the only counts below which some
+// game becomes impossible, and climbs the log-likelihood one colour at a time:
+// a move adds or removes cubes of one colour, or swaps cubes between two
+// colours so a fixed total can still be explored, and a move that helps is
+// repeated with doubling steps
+//
+// intervals come from the profile likelihood: a count stays in the interval
+// while the best bag with that count is within 3.84 / 2 of the maximum, the
+// 95% cut-off of a chi-squared with one degree of freedom. Each bound is
+// found by doubling the distance from the estimate until the profile drops
+// below the cut-off, then bisecting
+//
+// every bag is scored from a tally of the games rather than the games
+// themselves: the log-likelihood only depends on how many turns drew each
+// number of cubes of each colour, and how many turns drew each number of
+// cubes in all
+
+use super::probability::{ln_binomial, ln_factorial, Sampling};
+use super::{minimal_bag, Color, CubeSet, Game};
+use crate::error::AocError;
+use std::collections::BTreeMap;
+
+/// Half the 95% quantile of a chi-squared with one degree of freedom.
+const PROFILE_CUTOFF: f64 = 3.841 / 2.0;
+
+/// Smallest gain in log-likelihood that counts as an improvement.
+const EPSILON: f64 = 1e-9;
+
+/// Belief about the total number of cubes before seeing any game.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TotalPrior {
+    /// Every total up to the estimator's maximum is equally likely.
+    #[default]
+    Flat,
+    /// Poisson with the given mean, which must be positive and finite.
+    Poisson { mean: f64 },
+}
+
+impl TotalPrior {
+    fn log_density(&self, total: u64) -> f64 {
+        match *self {
+            TotalPrior::Flat => 0.0,
+            // the factorial term matters, it is what changes with total
+            TotalPrior::Poisson { mean } => total as f64 * mean.ln() - mean - ln_factorial(total),
+        }
+    }
+}
+
+/// Range of plausible counts for one colour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorInterval {
+    pub color: Color,
+    pub estimate: u32,
+    pub lower: u32,
+    /// Equal to the estimator's maximum total when the data does not bound
+    /// the count from above.
+    pub upper: u32,
+}
+
+/// Most likely bag with its score and per colour intervals.
+#[derive(Debug, Clone)]
+pub struct BagEstimate {
+    bag: CubeSet,
+    log_posterior: f64,
+    intervals: Vec<ColorInterval>,
+}
+
+impl BagEstimate {
+    pub fn bag(&self) -> &CubeSet {
+        &self.bag
+    }
+
+    /// Log-likelihood plus log prior of the estimate.
+    pub fn log_posterior(&self) -> f64 {
+        self.log_posterior
+    }
+
+    pub fn intervals(&self) -> &[ColorInterval] {
+        &self.intervals
+    }
+}
+
+/// Maximum-likelihood (or, with a prior, maximum a posteriori) estimate of
+/// the bag behind a set of games.
+#[derive(Debug, Clone)]
+pub struct Estimator {
+    sampling: Sampling,
+    prior: TotalPrior,
+    max_total: u64,
+}
+
+impl Default for Estimator {
+    fn default() -> Self {
+        Self {
+            sampling: Sampling::default(),
+            prior: TotalPrior::default(),
+            max_total: 500,
+        }
+    }
+}
+
+impl Estimator {
+    pub fn new(sampling: Sampling) -> Self {
+        Self {
+            sampling,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_prior(mut self, prior: TotalPrior) -> Self {
+        self.prior = prior;
+        self
+    }
+
+    /// Largest bag considered. Without a prior the likelihood can keep
+    /// growing with the bag, so the search needs a bound.
+    pub fn with_max_total(mut self, max_total: u64) -> Self {
+        self.max_total = max_total;
+        self
+    }
+
+    /// Fails when even the minimal bag is larger than the maximum total.
+    ///
+    /// Drawing with replacement, the games only tell the proportions of the
+    /// colours apart, not the size of the bag, so a `Poisson` prior is
+    /// required to pick one. Without replacement the size is estimated, but
+    /// an estimate using up the whole maximum means the games do not bound
+    /// it and only the proportions are meaningful.
+    pub fn estimate(&self, games: &[Game]) -> Result<BagEstimate, AocError> {
+        if self.sampling == Sampling::WithReplacement && self.prior == TotalPrior::Flat {
+            return Err(AocError::Validation(
+                "drawing with replacement the bag size cannot be estimated, \
+                 a Poisson prior on the total is required"
+                    .to_string(),
+            ));
+        }
+        if let TotalPrior::Poisson { mean } = self.prior {
+            if !(mean.is_finite() && mean > 0.0) {
+                return Err(AocError::Validation(format!(
+                    "the Poisson prior needs a positive mean, got {}",
+                    mean
+                )));
+            }
+        }
+        let start = minimal_bag(games);
+        if start.total() > self.max_total {
+            return Err(AocError::Validation(format!(
+                "the games need at least {} cubes, more than the maximum of {}",
+                start.total(),
+                self.max_total
+            )));
+        }
+        let colors: Vec<Color> = start.iter().map(|(color, _)| color).collect();
+        let search = Search {
+            tally: Tally::new(games, &colors, self.sampling),
+            colors: &colors,
+            estimator: self,
+        };
+
+        let counts: Vec<u32> = start.iter().map(|(_, count)| count).collect();
+        let (counts, best) = search.climb(counts, None);
+        let intervals = (0..colors.len())
+            .map(|i| search.interval(&counts, best, i))
+            .collect();
+
+        Ok(BagEstimate {
+            bag: search.cubes(&counts),
+            log_posterior: best,
+            intervals,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Move {
+    Add(usize),
+    Remove(usize),
+    Transfer {
+        to: usize,
+        from: usize,
+    },
+    /// Grows or shrinks the free colours together, keeping their
+    /// proportions, which single colour moves cannot do when only the
+    /// proportions are identifiable.
+    Scale {
+        grow: bool,
+    },
+}
+
+impl Move {
+    /// Counts after moving `step` cubes, `None` when it is not possible.
+    fn apply(self, counts: &[u32], free: &[usize], step: u32) -> Option<Vec<u32>> {
+        let mut moved = counts.to_vec();
+        match self {
+            Move::Add(i) => moved[i] = moved[i].checked_add(step)?,
+            Move::Remove(i) => moved[i] = moved[i].checked_sub(step)?,
+            Move::Transfer { to, from } => {
+                moved[from] = moved[from].checked_sub(step)?;
+                moved[to] = moved[to].checked_add(step)?;
+            }
+            Move::Scale { grow } => {
+                let total: u64 = free.iter().map(|&i| u64::from(counts[i])).sum();
+                let target = if grow {
+                    total + u64::from(step)
+                } else {
+                    total.checked_sub(u64::from(step)).filter(|&t| t > 0)?
+                };
+                for &i in free {
+                    let scaled = (u64::from(counts[i]) * target + total / 2) / total;
+                    moved[i] = u32::try_from(scaled).ok()?;
+                }
+                if moved == counts {
+                    return None;
+                }
+            }
+        }
+        Some(moved)
+    }
+}
+
+/// What the log-likelihood of a bag depends on, gathered once from the
+/// games.
+struct Tally {
+    sampling: Sampling,
+    /// For each colour, `(k, turns)` pairs: the number of turns drawing
+    /// exactly `k > 0` cubes of the colour.
+    draws: Vec<Vec<(u64, u64)>>,
+    /// For each colour, the cubes of it drawn over every turn.
+    drawn: Vec<u64>,
+    /// `(n, turns)` pairs: the number of turns drawing `n > 0` cubes.
+    sizes: Vec<(u64, u64)>,
+    /// Terms that do not depend on the bag.
+    constant: f64,
+}
+
+impl Tally {
+    /// `colors` must hold every colour the games draw.
+    fn new(games: &[Game], colors: &[Color], sampling: Sampling) -> Self {
+        let mut draws = vec![BTreeMap::new(); colors.len()];
+        let mut drawn = vec![0; colors.len()];
+        let mut sizes = BTreeMap::new();
+        let mut constant = 0.0;
+        for turn in games.iter().flat_map(Game::turns) {
+            let n = turn.cubes().total();
+            if n == 0 {
+                continue;
+            }
+            *sizes.entry(n).or_insert(0) += 1;
+            constant += ln_factorial(n);
+            for (color, k) in turn.cubes().iter() {
+                let i = colors.iter().position(|c| *c == color).unwrap();
+                *draws[i].entry(u64::from(k)).or_insert(0) += 1;
+                drawn[i] += u64::from(k);
+                constant -= ln_factorial(k.into());
+            }
+        }
+        Self {
+            sampling,
+            draws: draws.into_iter().map(|d| d.into_iter().collect()).collect(),
+            drawn,
+            sizes: sizes.into_iter().collect(),
+            constant,
+        }
+    }
+
+    /// Same as `log_likelihood` over the games, for a bag with `counts` of
+    /// each colour.
+    fn log_likelihood(&self, counts: &[u32]) -> f64 {
+        let total: u64 = counts.iter().map(|&c| u64::from(c)).sum();
+        match self.sampling {
+            // sum over turns of prod C(K_c, k_c) / C(N, n)
+            Sampling::WithoutReplacement => {
+                let ways: f64 = self
+                    .draws
+                    .iter()
+                    .zip(counts)
+                    .flat_map(|(draws, &count)| {
+                        draws
+                            .iter()
+                            .map(move |&(k, turns)| turns as f64 * ln_binomial(count.into(), k))
+                    })
+                    .sum();
+                // a draw larger than the bag makes the handfuls term
+                // infinite too, checking first keeps NaN out
+                if ways == f64::NEG_INFINITY {
+                    return ways;
+                }
+                let handfuls: f64 = self
+                    .sizes
+                    .iter()
+                    .map(|&(n, turns)| turns as f64 * ln_binomial(total, n))
+                    .sum();
+                ways - handfuls
+            }
+            // sum over turns of n! / prod k_c! * prod (K_c / N)^k_c
+            Sampling::WithReplacement => {
+                let mut log_p = self.constant;
+                for (&drawn, &count) in self.drawn.iter().zip(counts) {
+                    if drawn > 0 {
+                        if count == 0 {
+                            return f64::NEG_INFINITY;
+                        }
+                        log_p += drawn as f64 * (f64::from(count) / total as f64).ln();
+                    }
+                }
+                log_p
+            }
+        }
+    }
+}
+
+struct Search<'a> {
+    tally: Tally,
+    colors: &'a [Color],
+    estimator: &'a Estimator,
+}
+
+impl Search<'_> {
+    fn cubes(&self, counts: &[u32]) -> CubeSet {
+        self.colors
+            .iter()
+            .cloned()
+            .zip(counts.iter().copied())
+            .collect()
+    }
+
+    fn score(&self, counts: &[u32]) -> f64 {
+        let total: u64 = counts.iter().map(|&c| u64::from(c)).sum();
+        if total > self.estimator.max_total {
+            return f64::NEG_INFINITY;
+        }
+        self.tally.log_likelihood(counts) + self.estimator.prior.log_density(total)
+    }
+
+    /// Coordinate ascent from `counts`, leaving colour `fixed` alone.
+    fn climb(&self, mut counts: Vec<u32>, fixed: Option<usize>) -> (Vec<u32>, f64) {
+        let free: Vec<usize> = (0..counts.len()).filter(|&i| Some(i) != fixed).collect();
+        let mut moves = vec![Move::Scale { grow: true }, Move::Scale { grow: false }];
+        for &i in &free {
+            moves.push(Move::Add(i));
+            moves.push(Move::Remove(i));
+            for &j in &free {
+                if i != j {
+                    moves.push(Move::Transfer { to: i, from: j });
+                }
+            }
+        }
+
+        let mut best = self.score(&counts);
+        loop {
+            let mut improved = false;
+            for &mv in &moves {
+                let mut step = self.first_step(mv, &counts, &free, best);
+                while let Some(candidate) = mv.apply(&counts, &free, step) {
+                    let score = self.score(&candidate);
+                    if score <= best + EPSILON {
+                        break;
+                    }
+                    counts = candidate;
+                    best = score;
+                    improved = true;
+                    step *= 2;
+                }
+            }
+            if !improved {
+                return (counts, best);
+            }
+        }
+    }
+
+    /// Step to start `mv` with. Scaling by one cube at a time rounds the
+    /// proportions off, so the smallest step that helps is looked for, up to
+    /// doubling the free cubes.
+    fn first_step(&self, mv: Move, counts: &[u32], free: &[usize], best: f64) -> u32 {
+        if !matches!(mv, Move::Scale { .. }) {
+            return 1;
+        }
+        let total: u32 = free.iter().map(|&i| counts[i]).sum();
+        (1..=total.max(1))
+            .find(|&step| {
+                mv.apply(counts, free, step)
+                    .is_some_and(|candidate| self.score(&candidate) > best + EPSILON)
+            })
+            .unwrap_or(1)
+    }
+
+    /// Profile likelihood interval of colour `i` around the estimate.
+    fn interval(&self, estimate: &[u32], best: f64, i: usize) -> ColorInterval {
+        let max = self.estimator.max_total.min(u64::from(u32::MAX)) as u32;
+        ColorInterval {
+            color: self.colors[i].clone(),
+            estimate: estimate[i],
+            lower: estimate[i] - self.bound(estimate, best, i, estimate[i], |d| estimate[i] - d),
+            upper: estimate[i]
+                + self.bound(estimate, best, i, max - estimate[i], |d| estimate[i] + d),
+        }
+    }
+
+    /// Furthest distance up to `limit` from the estimate that colour `i` can
+    /// be moved while staying within the profile cut-off, with `value`
+    /// giving the count at a distance.
+    fn bound(
+        &self,
+        estimate: &[u32],
+        best: f64,
+        i: usize,
+        limit: u32,
+        value: impl Fn(u32) -> u32,
+    ) -> u32 {
+        // bags profiled at the closest distance known to be within, the
+        // next climb starts from there
+        let mut warm = estimate.to_vec();
+        let within = |distance: u32, warm: &mut Vec<u32>| {
+            let mut counts = warm.clone();
+            counts[i] = value(distance);
+            let (counts, score) = self.climb(counts, Some(i));
+            let inside = score >= best - PROFILE_CUTOFF;
+            if inside {
+                *warm = counts;
+            }
+            inside
+        };
+
+        let (mut inside, mut step) = (0, 1);
+        let mut outside = None;
+        while inside < limit {
+            let distance = inside.saturating_add(step).min(limit);
+            if within(distance, &mut warm) {
+                inside = distance;
+                step = step.saturating_mul(2);
+            } else {
+                outside = Some(distance);
+                break;
+            }
+        }
+        if let Some(mut outside) = outside {
+            while outside - inside > 1 {
+                let middle = inside + (outside - inside) / 2;
+                if within(middle, &mut warm) {
+                    inside = middle;
+                } else {
+                    outside = middle;
+                }
+            }
+        }
+        inside
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::parse_games;
+    use super::super::probability::log_likelihood;
+    use super::super::test::{rgb, simulate, EXAMPLE};
+    use super::*;
+    use crate::input;
+
+    #[test]
+    fn tally_matches_log_likelihood() {
+        let games = parse_games(EXAMPLE.as_bytes()).unwrap();
+        let colors = Color::KNOWN.to_vec();
+        for sampling in [Sampling::WithReplacement, Sampling::WithoutReplacement] {
+            let tally = Tally::new(&games, &colors, sampling);
+            for counts in [[20, 13, 15], [12, 13, 14], [0, 5, 5], [0, 0, 0]] {
+                let exact = log_likelihood(&games, &rgb(counts[0], counts[1], counts[2]), sampling);
+                let tallied = tally.log_likelihood(&counts);
+                if exact.is_finite() {
+                    assert!((exact - tallied).abs() < 1e-9, "{:?} {}", counts, tallied);
+                } else {
+                    assert_eq!(tallied, exact, "{:?}", counts);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn estimate_puzzle_input() {
+        let games = parse_games(input::open("data/day_2.txt").unwrap()).unwrap();
+        let estimators = [
+            Estimator::default(),
+            Estimator::new(Sampling::WithReplacement)
+                .with_prior(TotalPrior::Poisson { mean: 100.0 }),
+        ];
+        for estimator in estimators {
+            let estimate = estimator.estimate(&games).unwrap();
+            assert!(minimal_bag(&games).is_subset_of(estimate.bag()));
+            for interval in estimate.intervals() {
+                assert!(interval.lower <= interval.estimate && interval.estimate <= interval.upper);
+            }
+        }
+    }
+
+    #[test]
+    fn estimate_recovers_bag_without_replacement() {
+        let truth = rgb(6, 3, 9);
+        let games = simulate(&truth, 300, 8, Sampling::WithoutReplacement);
+        let estimate = Estimator::new(Sampling::WithoutReplacement)
+            .with_max_total(60)
+            .estimate(&games)
+            .unwrap();
+
+        assert!(minimal_bag(&games).is_subset_of(estimate.bag()));
+        for interval in estimate.intervals() {
+            let true_count = truth.get(&interval.color);
+            assert!(
+                (interval.lower..=interval.upper).contains(&true_count),
+                "{:?} misses {}",
+                interval,
+                true_count
+            );
+            assert!(interval.lower <= interval.estimate && interval.estimate <= interval.upper);
+        }
+    }
+
+    #[test]
+    fn estimate_prior_fixes_scale_with_replacement() {
+        // with replacement only the proportions are identifiable, the prior
+        // decides the size
+        let truth = rgb(10, 5, 5);
+        let games = simulate(&truth, 100, 4, Sampling::WithReplacement);
+        let estimate = Estimator::new(Sampling::WithReplacement)
+            .with_prior(TotalPrior::Poisson { mean: 40.0 })
+            .estimate(&games)
+            .unwrap();
+
+        let bag = estimate.bag();
+        assert!((36..=44).contains(&bag.total()), "{}", bag);
+        let red_share = f64::from(bag.get(&Color::Red)) / bag.total() as f64;
+        assert!((red_share - 0.5).abs() < 0.1, "{}", bag);
+        assert!(estimate.log_posterior().is_finite());
+    }
+
+    #[test]
+    fn estimate_respects_max_total() {
+        let games = simulate(&rgb(6, 3, 9), 10, 8, Sampling::WithoutReplacement);
+        let minimal = minimal_bag(&games).total();
+        let estimator = Estimator::default().with_max_total(minimal);
+        let estimate = estimator.estimate(&games).unwrap();
+        assert_eq!(estimate.bag(), &minimal_bag(&games));
+
+        assert!(Estimator::default()
+            .with_max_total(minimal - 1)
+            .estimate(&games)
+            .is_err());
+    }
+
+    #[test]
+    fn estimate_rejects_unusable_priors() {
+        let games = simulate(&rgb(10, 5, 5), 10, 4, Sampling::WithReplacement);
+        let err = Estimator::new(Sampling::WithReplacement)
+            .estimate(&games)
+            .unwrap_err();
+        assert!(matches!(err, AocError::Validation(_)));
+
+        for mean in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let estimate = Estimator::new(Sampling::WithReplacement)
+                .with_prior(TotalPrior::Poisson { mean })
+                .estimate(&games);
+            assert!(estimate.is_err(), "{}", mean);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+// every game needs at least its minimum set of cubes, and a bag admits the
+// game exactly when that minimum set fits in it
+//
+// an optimal bag only ever needs counts that some game requires, so the
+// searches below try the counts needed by the games still fitting for every
+// colour but the last, and pick the count of the last colour directly from
+// the games that still fit. a count no fitting game needs admits the same
+// games as the next smaller one with more cubes, so it is never tried
+//
+// both queries are NP-hard once the number of colours grows, so the games
+// examined are counted and a search giving up past `MAX_WORK` is an error
+
+use super::{Color, CubeSet, Game};
+use crate::error::AocError;
+use std::collections::BTreeSet;
+
+/// Most games a search examines before giving up, about half a second in a
+/// release build.
+const MAX_WORK: u64 = 1 << 25;
+
+/// Minimum cube counts of every game, one column per colour seen.
+struct Requirements {
+    colors: Vec<Color>,
+    games: Vec<(u32, Vec<u32>)>,
+}
+
+/// Games examined so far by a search, out of at most `limit`.
+struct Work {
+    examined: u64,
+    limit: u64,
+}
+
+impl Work {
+    fn new(limit: u64) -> Self {
+        Self { examined: 0, limit }
+    }
+
+    fn examine(&mut self, games: usize) -> Result<(), AocError> {
+        self.examined += games as u64;
+        if self.examined > self.limit {
+            return Err(AocError::Validation(format!(
+                "bag search gave up after examining {} games, \
+                 there are too many colours to search exhaustively",
+                self.limit
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Requirements {
+    fn new(games: &[Game]) -> Self {
+        let minimums: Vec<(u32, CubeSet)> = games
+            .iter()
+            .map(|game| (game.id(), game.take_most_from_turns().cubes().clone()))
+            .collect();
+        let colors: Vec<Color> = minimums
+            .iter()
+            .flat_map(|(_, cubes)| cubes.iter().map(|(color, _)| color))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let games = minimums
+            .into_iter()
+            .map(|(id, cubes)| (id, colors.iter().map(|c| cubes.get(c)).collect()))
+            .collect();
+        Self { colors, games }
+    }
+
+    fn all(&self) -> Vec<usize> {
+        (0..self.games.len()).collect()
+    }
+
+    fn need(&self, game: usize, i: usize) -> u32 {
+        self.games[game].1[i]
+    }
+
+    /// Counts of colour `i` needed by the `fitting` games, ascending.
+    fn candidates(&self, fitting: &[usize], i: usize) -> Vec<u32> {
+        let mut counts: Vec<u32> = fitting.iter().map(|&game| self.need(game, i)).collect();
+        counts.sort_unstable();
+        counts.dedup();
+        counts
+    }
+
+    /// The `fitting` games that need at most `count` cubes of colour `i`.
+    fn narrow(&self, fitting: &[usize], i: usize, count: u32) -> Vec<usize> {
+        fitting
+            .iter()
+            .copied()
+            .filter(|&game| self.need(game, i) <= count)
+            .collect()
+    }
+
+    fn cubes(&self, counts: &[u32]) -> CubeSet {
+        self.colors
+            .iter()
+            .cloned()
+            .zip(counts.iter().copied())
+            .collect()
+    }
+
+    fn search_smallest(
+        &self,
+        depth: usize,
+        k: usize,
+        fitting: &[usize],
+        thresholds: &mut Vec<u32>,
+        best: &mut Option<(u64, Vec<u32>)>,
+        work: &mut Work,
+    ) -> Result<(), AocError> {
+        work.examine(fitting.len())?;
+        let last = self.colors.len() - 1;
+        let partial: u64 = thresholds[..depth].iter().map(|&t| u64::from(t)).sum();
+        if depth == last {
+            let mut lasts: Vec<u32> = fitting.iter().map(|&game| self.need(game, last)).collect();
+            let (_, &mut count, _) = lasts.select_nth_unstable(k - 1);
+            let total = partial + u64::from(count);
+            if best
+                .as_ref()
+                .is_none_or(|(best_total, _)| total < *best_total)
+            {
+                thresholds[last] = count;
+                *best = Some((total, thresholds.clone()));
+            }
+            return Ok(());
+        }
+
+        for count in self.candidates(fitting, depth) {
+            if best
+                .as_ref()
+                .is_some_and(|(best_total, _)| partial + u64::from(count) >= *best_total)
+            {
+                break;
+            }
+            work.examine(fitting.len())?;
+            let narrowed = self.narrow(fitting, depth, count);
+            if narrowed.len() < k {
+                continue;
+            }
+            thresholds[depth] = count;
+            self.search_smallest(depth + 1, k, &narrowed, thresholds, best, work)?;
+        }
+        Ok(())
+    }
+
+    fn search_budget(
+        &self,
+        depth: usize,
+        remaining: u64,
+        fitting: &[usize],
+        thresholds: &mut Vec<u32>,
+        best: &mut (u64, u64, Vec<u32>),
+        work: &mut Work,
+    ) -> Result<(), AocError> {
+        work.examine(fitting.len())?;
+        // the games fitting so far bound the id sum of any bag below here
+        let bound: u64 = fitting
+            .iter()
+            .map(|&game| u64::from(self.games[game].0))
+            .sum();
+        let partial: u64 = thresholds[..depth].iter().map(|&t| u64::from(t)).sum();
+        if bound < best.0 || (bound == best.0 && partial >= best.1) {
+            return Ok(());
+        }
+
+        let last = self.colors.len() - 1;
+        if depth == last {
+            let count = fitting
+                .iter()
+                .map(|&game| self.need(game, last))
+                .filter(|&need| u64::from(need) <= remaining)
+                .max()
+                .unwrap_or(0);
+            let id_sum = self
+                .narrow(fitting, last, count)
+                .into_iter()
+                .map(|game| u64::from(self.games[game].0))
+                .sum();
+            thresholds[last] = count;
+            let total = partial + u64::from(count);
+            if id_sum > best.0 || (id_sum == best.0 && total < best.1) {
+                *best = (id_sum, total, thresholds.clone());
+            }
+            return Ok(());
+        }
+
+        for count in self.candidates(fitting, depth) {
+            if u64::from(count) > remaining {
+                break;
+            }
+            work.examine(fitting.len())?;
+            let narrowed = self.narrow(fitting, depth, count);
+            thresholds[depth] = count;
+            self.search_budget(
+                depth + 1,
+                remaining - u64::from(count),
+                &narrowed,
+                thresholds,
+                best,
+                work,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Smallest bag that admits every game.
+pub fn minimal_bag(games: &[Game]) -> CubeSet {
+    games.iter().fold(CubeSet::new(), |bag, game| {
+        bag.max(game.take_most_from_turns().cubes())
+    })
+}
+
+/// Bag with the fewest cubes in total that admits at least `k` of the
+/// games, `None` when there are fewer than `k` games.
+///
+/// The search is exhaustive and grows exponentially with the number of
+/// colours. It fails once it has examined `2^25` games, which the puzzle's
+/// three colours stay far below.
+pub fn smallest_bag_admitting(games: &[Game], k: usize) -> Result<Option<CubeSet>, AocError> {
+    smallest_bag_within(games, k, &mut Work::new(MAX_WORK))
+}
+
+fn smallest_bag_within(
+    games: &[Game],
+    k: usize,
+    work: &mut Work,
+) -> Result<Option<CubeSet>, AocError> {
+    if k > games.len() {
+        return Ok(None);
+    }
+    let requirements = Requirements::new(games);
+    if k == 0 || requirements.colors.is_empty() {
+        return Ok(Some(CubeSet::new()));
+    }
+
+    let mut thresholds = vec![0; requirements.colors.len()];
+    let mut best = None;
+    requirements.search_smallest(0, k, &requirements.all(), &mut thresholds, &mut best, work)?;
+    Ok(best.map(|(_, counts)| requirements.cubes(&counts)))
+}
+
+/// Bag of at most `budget` cubes maximising the sum of the ids of the games
+/// it admits, together with that sum. Ties go to the bag with fewer cubes.
+///
+/// Like `smallest_bag_admitting`, fails when the search examines more than
+/// `2^25` games.
+pub fn best_bag_within_budget(games: &[Game], budget: u64) -> Result<(CubeSet, u64), AocError> {
+    best_bag_within(games, budget, &mut Work::new(MAX_WORK))
+}
+
+fn best_bag_within(
+    games: &[Game],
+    budget: u64,
+    work: &mut Work,
+) -> Result<(CubeSet, u64), AocError> {
+    let requirements = Requirements::new(games);
+    if requirements.colors.is_empty() {
+        return Ok((
+            CubeSet::new(),
+            games.iter().map(|game| u64::from(game.id())).sum(),
+        ));
+    }
+
+    let mut thresholds = vec![0; requirements.colors.len()];
+    let mut best = (0, 0, thresholds.clone());
+    requirements.search_budget(
+        0,
+        budget,
+        &requirements.all(),
+        &mut thresholds,
+        &mut best,
+        work,
+    )?;
+    let bag = requirements.cubes(&best.2);
+    let id_sum = games
+        .iter()
+        .filter(|game| game.fits_in(&bag))
+        .map(|game| u64::from(game.id()))
+        .sum();
+    Ok((bag, id_sum))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::{rgb, EXAMPLE};
+    use super::super::{parse_games, SplitMix64};
+    use super::*;
+
+    fn example() -> Vec<Game> {
+        parse_games(EXAMPLE.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn inference_minimal_bag() {
+        let games = example();
+        let bag = minimal_bag(&games);
+        assert_eq!(bag, rgb(20, 13, 15));
+        assert!(games.iter().all(|game| game.fits_in(&bag)));
+        assert_eq!(minimal_bag(&[]), CubeSet::new());
+    }
+
+    #[test]
+    fn inference_smallest_bag_admitting() {
+        let games = example();
+        let smallest = |k| smallest_bag_admitting(&games, k).unwrap();
+        assert_eq!(smallest(0), Some(CubeSet::new()));
+        assert_eq!(smallest(1), Some(rgb(1, 3, 4)));
+        assert_eq!(smallest(3), Some(rgb(6, 3, 6)));
+        assert_eq!(smallest(5), Some(rgb(20, 13, 15)));
+        assert_eq!(smallest(6), None);
+    }
+
+    #[test]
+    fn inference_best_bag_within_budget() {
+        let games = example();
+        let best = |budget| best_bag_within_budget(&games, budget).unwrap();
+        assert_eq!(best(15), (rgb(6, 3, 6), 8));
+        assert_eq!(best(12), (rgb(6, 3, 2), 5));
+        assert_eq!(best(0), (CubeSet::new(), 0));
+        assert_eq!(best(1000).1, 15);
+
+        let games =
+            parse_games("Game 4294967295: 1 red\nGame 4294967294: 2 red\n".as_bytes()).unwrap();
+        assert_eq!(
+            best_bag_within_budget(&games, 2).unwrap(),
+            (rgb(2, 0, 0), 2 * u64::from(u32::MAX) - 1)
+        );
+    }
+
+    #[test]
+    fn inference_bounds_the_search() {
+        let colors = ["red", "green", "blue", "teal", "amber", "pink"];
+        let mut rng = SplitMix64::new(7);
+        let mut input = String::new();
+        for id in 1..=100 {
+            let draws: Vec<String> = colors
+                .iter()
+                .map(|color| format!("{} {}", rng.below(20) + 1, color))
+                .collect();
+            input += &format!("Game {}: {}\n", id, draws.join(", "));
+        }
+        let games = parse_games(input.as_bytes()).unwrap();
+        let bag = smallest_bag_admitting(&games, 50).unwrap().unwrap();
+        assert!(games.iter().filter(|game| game.fits_in(&bag)).count() >= 50);
+        let (bag, _) = best_bag_within_budget(&games, 60).unwrap();
+        assert!(bag.total() <= 60);
+
+        assert!(smallest_bag_within(&games, 50, &mut Work::new(1000)).is_err());
+        assert!(best_bag_within(&games, 60, &mut Work::new(1000)).is_err());
+    }
+}
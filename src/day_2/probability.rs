@@ -0,0 +1,305 @@
+// each turn is a handful drawn from the bag, and the cubes go back before
+// the next one, so turns are independent and a game's probability is the
+// product over its turns
+//
+// the size of a handful is taken as given: a turn's probability is that of
+// drawing exactly its colours among all handfuls of the same size
+//
+// without replacement that is the multivariate hypergeometric
+//     prod C(K_c, k_c) / C(N, n)
+// and with replacement the multinomial
+//     n! / prod k_c! * prod (K_c / N)^k_c
+// everything is computed in log space since games multiply many turns
+
+use super::{Bag, Color, CubeSet, Game, Turn};
+
+/// How the cubes of one handful are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sampling {
+    /// Every cube goes back into the bag before the next one is drawn.
+    WithReplacement,
+    /// The handful is drawn at once.
+    #[default]
+    WithoutReplacement,
+}
+
+pub(super) fn ln_factorial(n: u64) -> f64 {
+    if n < 32 {
+        return (2..=n).map(|i| (i as f64).ln()).sum();
+    }
+    // Stirling series, accurate to well below 1e-10 from here on
+    let n = n as f64;
+    n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln() + 1.0 / (12.0 * n)
+        - 1.0 / (360.0 * n.powi(3))
+}
+
+pub(super) fn ln_binomial(n: u64, k: u64) -> f64 {
+    if k > n {
+        f64::NEG_INFINITY
+    } else {
+        ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k)
+    }
+}
+
+/// Natural log of the probability of drawing `turn` from `bag`,
+/// `f64::NEG_INFINITY` when it cannot happen.
+pub fn turn_log_probability(turn: &Turn, bag: &CubeSet, sampling: Sampling) -> f64 {
+    let drawn = turn.cubes();
+    if !drawn.is_subset_of(bag) && sampling == Sampling::WithoutReplacement {
+        return f64::NEG_INFINITY;
+    }
+    let (n, total) = (drawn.total(), bag.total());
+    if n == 0 {
+        return 0.0;
+    }
+    if total == 0 {
+        return f64::NEG_INFINITY;
+    }
+
+    match sampling {
+        Sampling::WithoutReplacement => {
+            let ways: f64 = drawn
+                .iter()
+                .map(|(color, k)| ln_binomial(bag.get(&color).into(), k.into()))
+                .sum();
+            ways - ln_binomial(total, n)
+        }
+        Sampling::WithReplacement => {
+            let mut log_p = ln_factorial(n);
+            for (color, k) in drawn.iter() {
+                let available = bag.get(&color);
+                if available == 0 {
+                    return f64::NEG_INFINITY;
+                }
+                log_p += f64::from(k) * (f64::from(available) / total as f64).ln()
+                    - ln_factorial(k.into());
+            }
+            log_p
+        }
+    }
+}
+
+/// Natural log of the probability of the whole sequence of turns of `game`.
+pub fn game_log_probability(game: &Game, bag: &CubeSet, sampling: Sampling) -> f64 {
+    game.turns()
+        .iter()
+        .map(|turn| turn_log_probability(turn, bag, sampling))
+        .sum()
+}
+
+pub fn game_probability(game: &Game, bag: &CubeSet, sampling: Sampling) -> f64 {
+    game_log_probability(game, bag, sampling).exp()
+}
+
+/// Game ids with their log probability, most likely first. Impossible games
+/// come last, ties keep the order of `games`.
+pub fn rank_games(games: &[Game], bag: &CubeSet, sampling: Sampling) -> Vec<(u32, f64)> {
+    let mut ranked: Vec<(u32, f64)> = games
+        .iter()
+        .map(|game| (game.id(), game_log_probability(game, bag, sampling)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+/// Log-likelihood of `bag` given every game.
+pub fn log_likelihood(games: &[Game], bag: &CubeSet, sampling: Sampling) -> f64 {
+    games
+        .iter()
+        .map(|game| game_log_probability(game, bag, sampling))
+        .sum()
+}
+
+/// Candidate under which the games are most likely, with its
+/// log-likelihood. The first one wins ties.
+pub fn most_probable_bag<'b>(
+    games: &[Game],
+    candidates: &'b [Bag],
+    sampling: Sampling,
+) -> Option<(&'b Bag, f64)> {
+    candidates
+        .iter()
+        .map(|bag| (bag, log_likelihood(games, bag.cubes(), sampling)))
+        .reduce(|best, next| if next.1 > best.1 { next } else { best })
+}
+
+/// SplitMix64, small and good enough for reproducible simulations.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, `n` must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // multiply-shift, the bias is negligible for bag sized ranges
+        ((u128::from(self.next_u64()) * u128::from(n)) >> 64) as u64
+    }
+}
+
+/// The colour of every cube of `bag`, one entry per cube.
+pub(super) fn one_per_cube(bag: &CubeSet) -> Vec<Color> {
+    bag.iter()
+        .flat_map(|(color, count)| std::iter::repeat_n(color, count as usize))
+        .collect()
+}
+
+/// Draws one handful of `n` cubes, `cubes` holding one entry per cube.
+pub(super) fn draw(
+    cubes: &mut [Color],
+    n: usize,
+    sampling: Sampling,
+    rng: &mut SplitMix64,
+) -> CubeSet {
+    let mut handful = CubeSet::new();
+    for i in 0..n {
+        let picked = match sampling {
+            Sampling::WithReplacement => rng.below(cubes.len() as u64) as usize,
+            Sampling::WithoutReplacement => {
+                // partial Fisher-Yates, the first i slots are taken
+                let j = i + rng.below((cubes.len() - i) as u64) as usize;
+                cubes.swap(i, j);
+                i
+            }
+        };
+        let color = &cubes[picked];
+        handful.set(color.clone(), handful.get(color) + 1);
+    }
+    handful
+}
+
+/// Estimates the probability of `game` by replaying it `trials` times
+/// against `bag`. The same seed always gives the same estimate.
+pub fn monte_carlo_probability(
+    game: &Game,
+    bag: &CubeSet,
+    sampling: Sampling,
+    trials: u32,
+    seed: u64,
+) -> f64 {
+    let mut cubes = one_per_cube(bag);
+    let mut rng = SplitMix64::new(seed);
+
+    let possible = |n: u64| match sampling {
+        Sampling::WithReplacement => n == 0 || !cubes.is_empty(),
+        Sampling::WithoutReplacement => n <= cubes.len() as u64,
+    };
+    if trials == 0
+        || !game
+            .turns()
+            .iter()
+            .all(|turn| possible(turn.cubes().total()))
+    {
+        return 0.0;
+    }
+
+    let mut hits = 0;
+    for _ in 0..trials {
+        let matched = game.turns().iter().all(|turn| {
+            let n = turn.cubes().total() as usize;
+            &draw(&mut cubes, n, sampling, &mut rng) == turn.cubes()
+        });
+        if matched {
+            hits += 1;
+        }
+    }
+    f64::from(hits) / f64::from(trials)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::rgb;
+    use super::*;
+
+    fn game(line: &str) -> Game {
+        Game::from_line(line).unwrap()
+    }
+
+    fn assert_close(a: f64, b: f64, tolerance: f64) {
+        assert!((a - b).abs() < tolerance, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn probability_exact() {
+        use Sampling::*;
+        let bag = rgb(1, 0, 1);
+        let one = game("Game 1: 1 red");
+        let both = game("Game 2: 1 red, 1 blue");
+        assert_close(game_probability(&one, &bag, WithoutReplacement), 0.5, 1e-12);
+        assert_close(game_probability(&one, &bag, WithReplacement), 0.5, 1e-12);
+        assert_close(
+            game_probability(&both, &bag, WithoutReplacement),
+            1.0,
+            1e-12,
+        );
+        assert_close(game_probability(&both, &bag, WithReplacement), 0.5, 1e-12);
+
+        // 2 of 3 red and 1 of 2 blue out of 6 cubes: 3 * 2 / 20
+        let bag = rgb(3, 1, 2);
+        let game = game("Game 3: 2 red, 1 blue; 1 green");
+        assert_close(
+            game_probability(&game, &bag, WithoutReplacement),
+            0.3 / 6.0,
+            1e-12,
+        );
+
+        let impossible = Game::from_line("Game 4: 4 red").unwrap();
+        assert_eq!(game_probability(&impossible, &bag, WithoutReplacement), 0.0);
+        assert!(game_probability(&impossible, &bag, WithReplacement) > 0.0);
+    }
+
+    #[test]
+    fn probability_ln_factorial_is_continuous() {
+        let exact: f64 = (2..=40u64).map(|i| (i as f64).ln()).sum();
+        assert_close(ln_factorial(40), exact, 1e-9);
+        assert_close(ln_factorial(32) - ln_factorial(31), 32f64.ln(), 1e-9);
+    }
+
+    #[test]
+    fn probability_monte_carlo_agrees() {
+        let bag = rgb(3, 1, 2);
+        let game = game("Game 1: 2 red, 1 blue; 1 green");
+        for sampling in [Sampling::WithReplacement, Sampling::WithoutReplacement] {
+            let exact = game_probability(&game, &bag, sampling);
+            let estimate = monte_carlo_probability(&game, &bag, sampling, 50_000, 7);
+            assert_close(estimate, exact, 0.01);
+            assert_eq!(
+                estimate,
+                monte_carlo_probability(&game, &bag, sampling, 50_000, 7)
+            );
+        }
+    }
+
+    #[test]
+    fn probability_ranking() {
+        let games = vec![
+            game("Game 1: 5 red"),
+            game("Game 2: 1 red"),
+            game("Game 3: 1 purple"),
+        ];
+        let ranked = rank_games(&games, &rgb(4, 4, 4), Sampling::WithoutReplacement);
+        let order: Vec<u32> = ranked.iter().map(|&(id, _)| id).collect();
+        assert_eq!(order, vec![2, 1, 3]);
+        assert_eq!(ranked[2].1, f64::NEG_INFINITY);
+
+        let candidates = [
+            Bag::new("blue", rgb(1, 1, 10)),
+            Bag::new("red", rgb(10, 1, 1)),
+        ];
+        let (best, _) =
+            most_probable_bag(&games[..2], &candidates, Sampling::WithReplacement).unwrap();
+        assert_eq!(best.name(), "red");
+    }
+}
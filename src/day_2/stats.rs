@@ -0,0 +1,332 @@
+use super::{Bag, Color, CubeSet, Game};
+use std::fmt;
+
+/// Draw sizes of one colour across every turn that drew it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ColorStats {
+    pub color: Color,
+    /// Number of turns drawing the colour.
+    pub draws: usize,
+    pub max: u32,
+    pub mean: f64,
+    pub median: f64,
+    /// Limit of the bag the report was made for.
+    pub limit: u32,
+    /// Games drawing more cubes of this colour than the limit.
+    pub failing_games: Vec<u32>,
+}
+
+/// One game at a glance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GameSummary {
+    pub id: u32,
+    pub turns: usize,
+    pub minimum: CubeSet,
+    /// For a game the bag does not admit, the colour exceeding its limit by
+    /// the most cubes. Ties go to the colour listed first.
+    pub binding: Option<Color>,
+}
+
+/// Statistics over a set of games, checked against one bag.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GameStats {
+    bag: Bag,
+    colors: Vec<ColorStats>,
+    games: Vec<GameSummary>,
+}
+
+fn median(sorted: &[u32]) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => f64::from(sorted[n / 2]),
+        n => (f64::from(sorted[n / 2 - 1]) + f64::from(sorted[n / 2])) / 2.0,
+    }
+}
+
+impl GameStats {
+    pub fn new(games: &[Game], bag: &Bag) -> Self {
+        let summaries: Vec<GameSummary> = games
+            .iter()
+            .map(|game| {
+                let minimum = game.take_most_from_turns().cubes().clone();
+                let binding = (&minimum - bag.cubes())
+                    .iter()
+                    .reduce(|best, next| if next.1 > best.1 { next } else { best })
+                    .map(|(color, _)| color);
+                GameSummary {
+                    id: game.id(),
+                    turns: game.turns().len(),
+                    minimum,
+                    binding,
+                }
+            })
+            .collect();
+
+        let mut colors: Vec<Color> = bag.cubes().iter().map(|(color, _)| color).collect();
+        for summary in &summaries {
+            for (color, _) in summary.minimum.iter() {
+                if !colors.contains(&color) {
+                    colors.push(color);
+                }
+            }
+        }
+
+        let colors = colors
+            .into_iter()
+            .map(|color| {
+                let mut sizes: Vec<u32> = games
+                    .iter()
+                    .flat_map(|game| game.turns())
+                    .map(|turn| turn.cubes().get(&color))
+                    .filter(|&count| count > 0)
+                    .collect();
+                sizes.sort_unstable();
+                let limit = bag.cubes().get(&color);
+                let failing_games = summaries
+                    .iter()
+                    .filter(|summary| summary.minimum.get(&color) > limit)
+                    .map(|summary| summary.id)
+                    .collect();
+                ColorStats {
+                    draws: sizes.len(),
+                    max: sizes.last().copied().unwrap_or(0),
+                    mean: if sizes.is_empty() {
+                        0.0
+                    } else {
+                        sizes.iter().map(|&s| f64::from(s)).sum::<f64>() / sizes.len() as f64
+                    },
+                    median: median(&sizes),
+                    limit,
+                    failing_games,
+                    color,
+                }
+            })
+            .collect();
+
+        Self {
+            bag: bag.clone(),
+            colors,
+            games: summaries,
+        }
+    }
+
+    pub fn bag(&self) -> &Bag {
+        &self.bag
+    }
+
+    /// The bag's colours first, then any other colour drawn.
+    pub fn colors(&self) -> &[ColorStats] {
+        &self.colors
+    }
+
+    pub fn games(&self) -> &[GameSummary] {
+        &self.games
+    }
+
+    /// Ids of the games the bag does not admit.
+    pub fn invalid_games(&self) -> impl Iterator<Item = u32> + '_ {
+        self.games
+            .iter()
+            .filter(|summary| summary.binding.is_some())
+            .map(|summary| summary.id)
+    }
+
+    /// The report as JSON, the same with or without the `serde` feature.
+    pub fn to_json(&self) -> String {
+        #[cfg(feature = "serde")]
+        return serde_json::to_string(self).expect("statistics always serialize");
+        #[cfg(not(feature = "serde"))]
+        return json::to_string(self);
+    }
+}
+
+// without serde the report is written by hand, in the layout the derived
+// `Serialize` produces
+#[cfg(any(test, not(feature = "serde")))]
+mod json {
+    use super::{CubeSet, GameStats};
+    use std::fmt::Write;
+
+    pub(super) fn to_string(stats: &GameStats) -> String {
+        let mut out = String::new();
+        write!(
+            out,
+            "{{\"bag\":{{\"name\":{},\"cubes\":{}}},\"colors\":[",
+            string(stats.bag.name()),
+            cubes(stats.bag.cubes())
+        )
+        .unwrap();
+        for (i, color) in stats.colors.iter().enumerate() {
+            let failing: Vec<String> = color.failing_games.iter().map(u32::to_string).collect();
+            write!(
+                out,
+                "{}{{\"color\":{},\"draws\":{},\"max\":{},\"mean\":{},\"median\":{},\"limit\":{},\"failing_games\":[{}]}}",
+                if i > 0 { "," } else { "" },
+                string(color.color.name()),
+                color.draws,
+                color.max,
+                number(color.mean),
+                number(color.median),
+                color.limit,
+                failing.join(",")
+            )
+            .unwrap();
+        }
+        out.push_str("],\"games\":[");
+        for (i, game) in stats.games.iter().enumerate() {
+            write!(
+                out,
+                "{}{{\"id\":{},\"turns\":{},\"minimum\":{},\"binding\":{}}}",
+                if i > 0 { "," } else { "" },
+                game.id,
+                game.turns,
+                cubes(&game.minimum),
+                game.binding
+                    .as_ref()
+                    .map_or("null".to_string(), |color| string(color.name()))
+            )
+            .unwrap();
+        }
+        out.push_str("]}");
+        out
+    }
+
+    /// `s` as a JSON string, escaped the way serde_json does.
+    fn string(s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                '\u{8}' => out.push_str("\\b"),
+                '\u{c}' => out.push_str("\\f"),
+                c if u32::from(c) < 0x20 => write!(out, "\\u{:04x}", u32::from(c)).unwrap(),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    /// Floats keep their `.0`, and JSON has no NaN or infinity.
+    fn number(x: f64) -> String {
+        if x.is_finite() {
+            format!("{:?}", x)
+        } else {
+            "null".to_string()
+        }
+    }
+
+    fn cubes(cubes: &CubeSet) -> String {
+        let entries: Vec<String> = cubes
+            .iter()
+            .map(|(color, count)| format!("{}:{}", string(color.name()), count))
+            .collect();
+        format!("{{{}}}", entries.join(","))
+    }
+}
+
+impl fmt::Display for GameStats {
+    /// Two plain text tables, one row per colour and one per game.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "bag {}", self.bag)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<10} {:>6} {:>5} {:>7} {:>7} {:>6} {:>8}",
+            "colour", "draws", "max", "mean", "median", "limit", "failing"
+        )?;
+        for stats in &self.colors {
+            writeln!(
+                f,
+                "{:<10} {:>6} {:>5} {:>7.2} {:>7.1} {:>6} {:>8}",
+                stats.color.name(),
+                stats.draws,
+                stats.max,
+                stats.mean,
+                stats.median,
+                stats.limit,
+                stats.failing_games.len()
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>5} {:>6}  {:<30} {:<7}",
+            "game", "turns", "minimum", "binding"
+        )?;
+        for summary in &self.games {
+            writeln!(
+                f,
+                "{:>5} {:>6}  {:<30} {}",
+                summary.id,
+                summary.turns,
+                summary.minimum.to_string(),
+                summary.binding.as_ref().map_or("-", Color::name)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{parse_games, test::EXAMPLE};
+    use super::*;
+
+    #[test]
+    fn stats_example() {
+        let games = parse_games(EXAMPLE.as_bytes()).unwrap();
+        let stats = GameStats::new(&games, &Bag::puzzle());
+
+        let red = &stats.colors()[0];
+        assert_eq!(red.color, Color::Red);
+        // red draws: 4 1 1 20 4 1 3 6 14 6 1
+        assert_eq!((red.draws, red.max, red.median), (11, 20, 4.0));
+        assert!((red.mean - 61.0 / 11.0).abs() < 1e-12);
+        assert_eq!(red.failing_games, vec![3, 4]);
+        assert_eq!(stats.colors()[2].failing_games, vec![4]);
+
+        let turns: Vec<usize> = stats.games().iter().map(|g| g.turns).collect();
+        assert_eq!(turns, vec![3, 3, 3, 3, 2]);
+        assert_eq!(stats.invalid_games().collect::<Vec<_>>(), vec![3, 4]);
+        // game 4 is 2 red and 1 blue over the limits
+        assert_eq!(stats.games()[2].binding, Some(Color::Red));
+        assert_eq!(stats.games()[3].binding, Some(Color::Red));
+    }
+
+    #[test]
+    fn stats_render() {
+        let games = parse_games("Game 1: 2 red, 1 \"odd\"\n".as_bytes()).unwrap();
+        let stats = GameStats::new(&games, &Bag::puzzle());
+        let json = stats.to_json();
+        assert!(json.starts_with("{\"bag\":{\"name\":\"puzzle\""));
+        assert!(json.contains("{\"color\":\"\\\"odd\\\"\",\"draws\":1,\"max\":1,\"mean\":1.0,\"median\":1.0,\"limit\":0,\"failing_games\":[1]}"));
+        assert!(json.ends_with("\"binding\":\"\\\"odd\\\"\"}]}"));
+
+        let text = stats.to_string();
+        assert!(text
+            .lines()
+            .any(|line| line.starts_with("red") && line.ends_with(" 0")));
+        assert!(text.lines().last().unwrap().ends_with("\"odd\""));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stats_json_matches_serde() {
+        let input = "Game 1: 2 red, 1 \"odd\"\nGame 2: 3 back\\slash\u{1}, 5 blue; 4 red\n";
+        let games = parse_games(input.as_bytes()).unwrap();
+        for bag in [Bag::puzzle(), Bag::from_spec("red=1,teal=3").unwrap()] {
+            let stats = GameStats::new(&games, &bag);
+            assert_eq!(json::to_string(&stats), stats.to_json());
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use super::Color;
+use std::fmt;
+
+/// One colour of one turn drawing more cubes than the bag holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Violation {
+    /// Index of the turn in the game, starting at 0.
+    pub turn: usize,
+    pub color: Color,
+    pub observed: u32,
+    pub limit: u32,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "turn {} drew {} {}, the bag holds {}",
+            self.turn + 1,
+            self.observed,
+            self.color,
+            self.limit
+        )
+    }
+}
+
+/// Outcome of checking a game against a bag, with every violation found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Validation {
+    game: u32,
+    violations: Vec<Violation>,
+}
+
+impl Validation {
+    pub(super) fn new(game: u32, violations: Vec<Violation>) -> Self {
+        Self { game, violations }
+    }
+
+    pub fn game(&self) -> u32 {
+        self.game
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Violations by turn, then by colour with red, green and blue first.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "game {} is possible", self.game);
+        }
+        write!(f, "game {} is impossible:", self.game)?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            let separator = if i == 0 { " " } else { "; " };
+            write!(f, "{}{}", separator, violation)?;
+        }
+        Ok(())
+    }
+}
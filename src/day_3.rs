@@ -19,242 +19,201 @@
 //
 //
 
-// number in the grid gets its signature which is a vec of tuples
-// the row in first part will probably will be redundant but let's go with it in case
-// the second part has multi-row numbers
+// every number in the grid is tokenized once, row by row, into a span
+// (row, column range) with an id
+// a second grid maps each cell to the id of the number covering it
 //
 // get numbers
 // go to the symbol location
-// for each adjacent symbol construct a number ()
-//  the number is constructed by searching contiguous sequence of digits and then reordering the
-//  signature by sorting by row and column ascending
-
-use std::collections::VecDeque;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
-
-#[derive(Debug, Clone)]
+// look up the ids of the neighbouring cells, a number touching the symbol
+// with several digits shows up once per digit so the ids get deduplicated
+
+use crate::error::AocError;
+use crate::grid::{Grid, Neighbourhood};
+use crate::input;
+use crate::solution::{Solution, SolutionResult};
+use std::io::BufRead;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Number {
+    id: usize,
     val: u32,
-    signature: Vec<(usize, usize)>,
+    row: usize,
+    cols: Range<usize>,
 }
 
 impl Number {
-    fn unique_numbers(numbers: Vec<Number>) -> Vec<Number> {
-        let mut unique_numbers = Vec::new();
+    pub fn id(&self) -> usize {
+        self.id
+    }
 
-        for number in numbers {
-            if !(&unique_numbers).contains(&number) {
-                unique_numbers.push(number);
-            }
-        }
+    pub fn val(&self) -> u32 {
+        self.val
+    }
+
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn cols(&self) -> Range<usize> {
+        self.cols.clone()
+    }
 
-        unique_numbers
+    /// Cells covered by the number, left to right.
+    pub fn signature(&self) -> Vec<(usize, usize)> {
+        self.cols.clone().map(|col| (self.row, col)).collect()
     }
 }
-impl PartialEq for Number {
-    fn eq(&self, other: &Self) -> bool {
-        self.val == other.val && self.signature == other.signature
+
+/// Scans the grid once and returns every number together with a grid
+/// mapping each cell to the id of the number covering it.
+fn tokenize_numbers(grid: &Grid<char>) -> Result<(Vec<Number>, Grid<Option<usize>>), AocError> {
+    let mut numbers: Vec<Number> = vec![];
+    let mut ids = Vec::with_capacity(grid.height());
+
+    for (row, cells) in grid.rows().enumerate() {
+        let mut row_ids = vec![None; cells.len()];
+        let mut col = 0;
+        while col < cells.len() {
+            if !cells[col].is_ascii_digit() {
+                col += 1;
+                continue;
+            }
+
+            let start = col;
+            let mut val: u32 = 0;
+            while col < cells.len() && cells[col].is_ascii_digit() {
+                let digit = cells[col].to_digit(10).unwrap();
+                val = val
+                    .checked_mul(10)
+                    .and_then(|v| v.checked_add(digit))
+                    .ok_or_else(|| AocError::Parse {
+                        line: row + 1,
+                        column: start + 1,
+                        text: cells[start..]
+                            .iter()
+                            .take_while(|c| c.is_ascii_digit())
+                            .collect(),
+                        message: "number too large".to_string(),
+                    })?;
+                row_ids[col] = Some(numbers.len());
+                col += 1;
+            }
+
+            numbers.push(Number {
+                id: numbers.len(),
+                val,
+                row,
+                cols: start..col,
+            });
+        }
+        ids.push(row_ids);
     }
+
+    Ok((numbers, Grid::from_rows(ids)?))
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpecialSymbol {
     row: usize,
     col: usize,
 }
 
-pub enum WalkDirection {
-    Left,
-    Right,
-}
-
-#[derive(Debug)]
-pub struct EngineSchematic<'a> {
-    filepath: &'a str,
+/// Serialized as its rows and numbers. The numbers are derived from the rows,
+/// so they are recomputed when deserializing.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SchematicRows", try_from = "SchematicRows")
+)]
+pub struct EngineSchematic {
+    grid: Grid<char>,
+    numbers: Vec<Number>,
+    number_ids: Grid<Option<usize>>,
 }
 
-impl<'a> EngineSchematic<'a> {
-    pub fn new(filepath: &'a str) -> io::Result<Self> {
-        let _ = File::open(filepath)?;
-        Ok(Self { filepath })
+impl EngineSchematic {
+    pub fn new(filepath: &str) -> Result<Self, AocError> {
+        Self::from_reader(input::open(filepath)?)
     }
 
-    pub fn get_row(&self, row: usize) -> Option<String> {
-        let file = File::open(&self.filepath).unwrap(); // the check happens at struct creation
-        let reader = BufReader::new(file);
-
-        reader
-            .lines()
-            .nth(row) // Get the line at the specified index
-            .and_then(|line_result| line_result.ok()) // Convert Result to Option
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, AocError> {
+        let rows = reader.lines().collect::<Result<Vec<_>, _>>()?;
+        Self::from_rows(rows)
     }
 
-    pub fn get_char(&self, row: usize, col: usize) -> Option<char> {
-        self.get_row(row).and_then(|r| r.chars().nth(col))
+    fn from_rows(rows: Vec<String>) -> Result<Self, AocError> {
+        let grid = Grid::from_lines(rows)?;
+        let (numbers, number_ids) = tokenize_numbers(&grid)?;
+        Ok(Self {
+            grid,
+            numbers,
+            number_ids,
+        })
     }
 
-    pub fn find_special_symbols(&self) -> Vec<SpecialSymbol> {
-        let mut i = 0;
-        let mut special_symbols = vec![];
-        while let Some(_) = self.get_row(i) {
-            let mut j = 0;
-            while let Some(ch) = self.get_char(i, j) {
-                match ch {
-                    _ if ('0'..='9').contains(&ch) => (),
-                    '.' => (),
-                    _ => {
-                        special_symbols.push(SpecialSymbol { row: i, col: j });
-                    }
-                }
-                j += 1;
-            }
-            i += 1;
-        }
-        special_symbols
+    pub fn numbers(&self) -> &[Number] {
+        &self.numbers
     }
 
-    pub fn find_gears(&self) -> Vec<SpecialSymbol> {
-        let mut i = 0;
-        let mut special_symbols = vec![];
-        while let Some(_) = self.get_row(i) {
-            let mut j = 0;
-            while let Some(ch) = self.get_char(i, j) {
-                match ch {
-                    '*' => special_symbols.push(SpecialSymbol { row: i, col: j }),
-                    _ => (),
-                }
-                j += 1;
-            }
-            i += 1;
-        }
-        special_symbols
-    }
-
-    pub fn number_walk(
-        &self,
-        row: usize,
-        col: usize,
-        digits: &mut VecDeque<char>,
-        signature: &mut VecDeque<(usize, usize)>,
-        direction: WalkDirection,
-    ) {
-        let maybe_digit = self.get_char(row, col);
-
-        if maybe_digit.is_none() {
-            // out of bounds
-            return;
-        }
-
-        let maybe_digit = maybe_digit.unwrap();
+    pub fn grid(&self) -> &Grid<char> {
+        &self.grid
+    }
 
-        if !maybe_digit.is_digit(10) {
-            return;
-        }
+    pub fn get_row(&self, row: usize) -> Option<String> {
+        self.grid.row(row).map(|r| r.iter().collect())
+    }
 
-        let digit = maybe_digit;
-
-        match direction {
-            WalkDirection::Left => {
-                digits.push_front(digit);
-                signature.push_front((row, col));
-                if col == 0 {
-                    // wouldn't want to overflow
-                    return;
-                }
-                self.number_walk(row, col - 1, digits, signature, direction);
-            }
-            WalkDirection::Right => {
-                digits.push_back(digit);
-                signature.push_back((row, col));
-                if col == usize::MAX {
-                    return;
-                }
-                self.number_walk(row, col + 1, digits, signature, direction);
-            }
-        }
+    pub fn get_char(&self, row: usize, col: usize) -> Option<char> {
+        self.grid.get(row, col).copied()
     }
 
-    fn vecdeque_to_number(vecdeque: VecDeque<char>) -> u32 {
-        vecdeque
+    fn find_symbols(&self, is_symbol: impl Fn(char) -> bool) -> Vec<SpecialSymbol> {
+        self.grid
             .iter()
-            .fold(0u32, |acc, &c| acc * 10 + c.to_digit(10).unwrap() as u32)
+            .filter(|(_, &ch)| is_symbol(ch))
+            .map(|((row, col), _)| SpecialSymbol { row, col })
+            .collect()
     }
 
-    pub fn get_number(&self, row: usize, col: usize) -> Option<Number> {
-        let mut digits: VecDeque<char> = VecDeque::new();
-        let mut signature_deque: VecDeque<(usize, usize)> = VecDeque::new();
-
-        // root step
-        let maybe_digit = self.get_char(row, col);
+    pub fn find_special_symbols(&self) -> Vec<SpecialSymbol> {
+        self.find_symbols(|ch| !ch.is_ascii_digit() && ch != '.')
+    }
 
-        if maybe_digit.is_none() {
-            // out of bounds
-            return None;
-        }
+    pub fn find_gears(&self) -> Vec<SpecialSymbol> {
+        self.find_symbols(|ch| ch == '*')
+    }
 
-        let maybe_digit = maybe_digit.unwrap();
+    pub fn get_number(&self, row: usize, col: usize) -> Option<&Number> {
+        let id = (*self.number_ids.get(row, col)?)?;
+        self.numbers.get(id)
+    }
 
-        if !maybe_digit.is_digit(10) {
-            return None;
-        }
+    /// Ids of the numbers touching the symbol, each listed once.
+    fn adjacent_number_ids(&self, symbol: &SpecialSymbol) -> Vec<usize> {
+        let mut ids: Vec<usize> = self
+            .grid
+            .neighbours((symbol.row, symbol.col), &Neighbourhood::Moore)
+            .filter_map(|(row, col)| *self.number_ids.get(row, col)?)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
 
-        let digit = maybe_digit;
-
-        digits.push_front(digit);
-        signature_deque.push_front((row, col));
-
-        self.number_walk(
-            row,
-            col + 1,
-            &mut digits,
-            &mut signature_deque,
-            WalkDirection::Right,
-        );
-        self.number_walk(
-            row,
-            col - 1,
-            &mut digits,
-            &mut signature_deque,
-            WalkDirection::Left,
-        );
-
-        let val = EngineSchematic::vecdeque_to_number(digits);
-        let signature = Vec::from(signature_deque);
-        Some(Number { val, signature })
-    }
-
-    pub fn get_adjacent_numbers_for_symbol(&self, symbol: &SpecialSymbol) -> Vec<Number> {
-        let mut numbers: Vec<Number> = vec![];
-        let values: [i32; 3] = [-1, 0, 1];
-        for &i in &values {
-            for &j in &values {
-                if let (0, 0) = (i, j) {
-                    continue;
-                }
-
-                // Check for potential negative indices and skip if found
-                if (i < 0 && symbol.row < i.abs() as usize)
-                    || (j < 0 && symbol.col < j.abs() as usize)
-                {
-                    continue;
-                }
-
-                // Perform addition with type conversion
-                let row_index = (symbol.row as i32 + i) as usize;
-                let col_index = (symbol.col as i32 + j) as usize;
-
-                let maybe_number = self.get_number(row_index, col_index);
-                if let Some(number) = maybe_number {
-                    numbers.push(number);
-                }
-            }
-        }
-        Number::unique_numbers(numbers)
+    pub fn get_adjacent_numbers_for_symbol(&self, symbol: &SpecialSymbol) -> Vec<&Number> {
+        self.adjacent_number_ids(symbol)
+            .into_iter()
+            .map(|id| &self.numbers[id])
+            .collect()
     }
 
     /// return the numbers if there are only two adjacent
-    pub fn get_two_part_number(&self, symbol: &SpecialSymbol) -> Option<Vec<Number>> {
+    pub fn get_two_part_number(&self, symbol: &SpecialSymbol) -> Option<Vec<&Number>> {
         let nums = self.get_adjacent_numbers_for_symbol(symbol);
         if nums.len() == 2 {
             Some(nums)
@@ -264,43 +223,104 @@ impl<'a> EngineSchematic<'a> {
     }
 }
 
-pub fn solution_1(filepath: &str) -> u32 {
-    let engine_schematic = EngineSchematic::new(filepath).expect("file not found");
-    let symbols = engine_schematic.find_special_symbols();
-    let mut total_nums: Vec<Number> = vec![];
-    for symbol in symbols {
-        let nums = engine_schematic.get_adjacent_numbers_for_symbol(&symbol);
-        for num in nums {
-            total_nums.push(num);
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SchematicRows {
+    rows: Vec<String>,
+    #[serde(default)]
+    numbers: Vec<Number>,
+}
+
+#[cfg(feature = "serde")]
+impl From<EngineSchematic> for SchematicRows {
+    fn from(schematic: EngineSchematic) -> Self {
+        Self {
+            rows: schematic
+                .grid
+                .rows()
+                .map(|row| row.iter().collect())
+                .collect(),
+            numbers: schematic.numbers,
         }
     }
-    let total_nums = Number::unique_numbers(total_nums);
-    let mut total: u32 = 0;
-    for num in total_nums {
-        total += num.val;
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SchematicRows> for EngineSchematic {
+    type Error = AocError;
+
+    fn try_from(repr: SchematicRows) -> Result<Self, AocError> {
+        Self::from_rows(repr.rows)
+    }
+}
+
+pub fn part_1_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    let engine_schematic = EngineSchematic::from_reader(reader)?;
+    let mut is_part_number = vec![false; engine_schematic.numbers().len()];
+    for symbol in engine_schematic.find_special_symbols() {
+        for id in engine_schematic.adjacent_number_ids(&symbol) {
+            is_part_number[id] = true;
+        }
     }
-    total
+    // a sum of u32 numbers, one per grid cell at most, cannot overflow u64
+    Ok(engine_schematic
+        .numbers()
+        .iter()
+        .filter(|num| is_part_number[num.id])
+        .map(|num| u64::from(num.val))
+        .sum())
 }
 
-pub fn solution_2(filepath: &str) -> u32 {
-    let engine_schematic = EngineSchematic::new(filepath).expect("file not found");
+pub fn part_2_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    let engine_schematic = EngineSchematic::from_reader(reader)?;
     let symbols = engine_schematic.find_gears();
-    let mut total_nums_two_part: Vec<Vec<Number>> = vec![];
+    let mut total: u64 = 0;
     for symbol in symbols {
-        let maybe_nums = engine_schematic.get_two_part_number(&symbol);
-        if let Some(nums) = maybe_nums {
-            total_nums_two_part.push(nums);
+        if let Some(nums) = engine_schematic.get_two_part_number(&symbol) {
+            // the product of two u32 always fits in a u64, the sum may not
+            let ratio = u64::from(nums[0].val) * u64::from(nums[1].val);
+            total = total.checked_add(ratio).ok_or_else(|| {
+                AocError::Validation(format!(
+                    "sum of gear ratios overflows u64 at row {}, column {}",
+                    symbol.row + 1,
+                    symbol.col + 1
+                ))
+            })?;
         }
     }
-    let mut total: u32 = 0;
-    for nums_two_part in total_nums_two_part {
-        let mut mult_total: u32 = 1;
-        for num in nums_two_part {
-            mult_total *= num.val;
-        }
-        total += mult_total;
+    Ok(total)
+}
+
+pub fn part_1(input: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input.as_bytes())
+}
+
+pub fn part_2(input: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input.as_bytes())
+}
+
+pub fn solution_1(filepath: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input::open(filepath)?)
+}
+
+pub fn solution_2(filepath: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input::open(filepath)?)
+}
+
+pub struct DayThree;
+
+impl Solution for DayThree {
+    fn day(&self) -> u8 {
+        3
+    }
+
+    fn part_one(&self, input: &str) -> SolutionResult {
+        Ok(part_1(input)?.into())
+    }
+
+    fn part_two(&self, input: &str) -> SolutionResult {
+        Ok(part_2(input)?.into())
     }
-    total
 }
 
 #[cfg(test)]
@@ -324,7 +344,7 @@ mod test {
     fn engine_get_symbols() {
         let engine_schematic = EngineSchematic::new(FILE_PATH).unwrap();
         let symbols = engine_schematic.find_special_symbols();
-        assert!(symbols.len() > 0);
+        assert!(!symbols.is_empty());
     }
 
     #[test]
@@ -351,15 +371,82 @@ mod test {
         assert_eq!(nums.len(), 2);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn engine_serde_round_trip() {
+        let engine_schematic = EngineSchematic::from_reader("467.\n..*.\n.35.".as_bytes()).unwrap();
+        let json = serde_json::to_value(&engine_schematic).unwrap();
+        assert_eq!(json["rows"][1], "..*.");
+        assert_eq!(json["numbers"][1]["cols"]["start"], 1);
+
+        let back: EngineSchematic = serde_json::from_value(json).unwrap();
+        assert_eq!(back.numbers(), engine_schematic.numbers());
+        assert!(serde_json::from_str::<EngineSchematic>(r#"{"rows":["..","."]}"#).is_err());
+    }
+
+    const EXAMPLE: &str = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+";
+
+    #[test]
+    fn day_3_example() {
+        assert_eq!(part_1(EXAMPLE).unwrap(), 4361);
+        assert_eq!(part_2(EXAMPLE).unwrap(), 467835);
+    }
+
+    #[test]
+    fn day_3_large_numbers() {
+        assert_eq!(part_2("999999*999999\n").unwrap(), 999_998_000_001);
+        // two gears of ratio (2^32 - 1)^2, kept apart by an empty row
+        let gear = "4294967295*4294967295";
+        let input = format!("{}\n{}\n{}\n", gear, ".".repeat(gear.len()), gear);
+        assert_eq!(part_1(&input).unwrap(), 4 * u64::from(u32::MAX));
+        assert!(part_2(&input).is_err());
+    }
+
+    #[test]
+    fn engine_tokenizes_numbers() {
+        let engine_schematic = EngineSchematic::from_reader(EXAMPLE.as_bytes()).unwrap();
+        let numbers = engine_schematic.numbers();
+        assert_eq!(numbers.len(), 10);
+        assert_eq!(numbers[1].val(), 114);
+        assert_eq!(numbers[1].row(), 0);
+        assert_eq!(numbers[1].cols(), 5..8);
+        assert_eq!(numbers[1].signature(), vec![(0, 5), (0, 6), (0, 7)]);
+
+        // every digit of a number maps back to the same id
+        let ids: Vec<usize> = (5..8)
+            .filter_map(|col| engine_schematic.get_number(0, col))
+            .map(Number::id)
+            .collect();
+        assert_eq!(ids, vec![1, 1, 1]);
+        assert!(engine_schematic.get_number(0, 4).is_none());
+    }
+
+    #[test]
+    fn engine_rejects_oversized_number() {
+        let result = EngineSchematic::from_reader("..99999999999*".as_bytes());
+        assert!(matches!(result, Err(AocError::Parse { column: 3, .. })));
+    }
+
     #[test]
     fn day_3_part_1() {
-        let sol = solution_1(FILE_PATH);
+        let sol = solution_1(FILE_PATH).unwrap();
         println!("Day 3 part 1: {}", sol);
     }
 
     #[test]
     fn day_3_part_2() {
-        let sol = solution_2(FILE_PATH);
+        let sol = solution_2(FILE_PATH).unwrap();
         println!("Day 3 part 2: {}", sol);
     }
 }
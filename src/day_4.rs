@@ -1,95 +1,174 @@
+use crate::error::AocError;
+use crate::input;
+use crate::parser::LineParser;
+use crate::solution::{Solution, SolutionResult};
 use std::collections::HashSet;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::BufRead;
+use std::str::FromStr;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Card {
     id: u32,
     winning_nums: Vec<u32>,
     your_nums: Vec<u32>,
 }
 
-impl Card {
-    pub fn from_str(s: &str) -> Self {
-        let colon_idx = s.find(":").expect("Colon found");
-        let card_id_unparsed = &s[..colon_idx];
-        let card_id_str = card_id_unparsed
-            .split(" ")
-            .last()
-            .expect(format!("card id invalid {}", card_id_unparsed).as_str());
-        let id = card_id_str
-            .parse::<u32>()
-            .expect(format!("Invalid card id format to parse to u32 {}", card_id_str).as_str());
+impl FromStr for Card {
+    type Err = AocError;
 
-        // winning | your_nums
-        let card_sides_unparsed = &s[colon_idx + 1..];
-        let mut card_sides_split = card_sides_unparsed.split("|");
-
-        let winning_side_unparsed = card_sides_split.nth(0).expect("winning side parse fail");
-        let your_side_unparsed = card_sides_split.last().expect("your side parse fail");
-
-        let winning_nums: Vec<u32> = winning_side_unparsed
-            .split_whitespace()
-            .into_iter()
-            .map(|num_unparsed| {
-                num_unparsed.parse::<u32>().expect(
-                    format!("Winning side - Invalid num to parse: {}", num_unparsed).as_str(),
-                )
-            })
-            .collect();
+    /// Parses `Card <id>: <winning numbers> | <your numbers>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = LineParser::new(s);
+        let id = parser.header("Card")?;
 
-        let your_nums: Vec<u32> = your_side_unparsed
-            .split_whitespace()
-            .into_iter()
-            .map(|num_unparsed| {
-                num_unparsed.parse::<u32>().expect(
-                    format!("Winning side - Invalid num to parse: {}", num_unparsed).as_str(),
-                )
-            })
-            .collect();
+        // winning | your_nums
+        let mut winning_nums = vec![];
+        while !parser.eat("|") {
+            if parser.is_at_end() {
+                return Err(parser.error_here("missing `|` separator"));
+            }
+            winning_nums.push(parser.number("number")?);
+        }
+        let mut your_nums = vec![];
+        while !parser.is_at_end() {
+            your_nums.push(parser.number("number")?);
+        }
 
-        Self {
+        Ok(Self {
             id,
             winning_nums,
             your_nums,
-        }
+        })
+    }
+}
+
+impl Card {
+    pub fn id(&self) -> u32 {
+        self.id
     }
 
     pub fn get_winning_numbers(&self) -> Vec<u32> {
         let winning_set: HashSet<u32> = self.winning_nums.iter().cloned().collect();
         let your_set: HashSet<u32> = self.your_nums.iter().cloned().collect();
 
-        winning_set
-            .intersection(&your_set)
-            .into_iter()
-            .cloned()
-            .collect()
+        winning_set.intersection(&your_set).cloned().collect()
     }
 
-    pub fn get_winning_points(&self) -> u32 {
+    /// Points of the card, doubling with every match after the first. Fails
+    /// when they do not fit in a `u64`.
+    pub fn get_winning_points(&self) -> Result<u64, AocError> {
         let winning_numbers_count = self.get_winning_numbers().len();
         if winning_numbers_count == 0 {
-            0
-        } else {
-            2u32.pow((winning_numbers_count as u32) - 1)
+            return Ok(0);
         }
+        u32::try_from(winning_numbers_count - 1)
+            .ok()
+            .and_then(|exponent| 2u64.checked_pow(exponent))
+            .ok_or_else(|| AocError::Validation(format!("points of card {} overflow u64", self.id)))
+    }
+}
+
+pub fn part_1_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    let mut total: u64 = 0;
+    for line in input::numbered_lines(reader) {
+        let (line_number, ip) = line?;
+        let card = Card::from_str(&ip).map_err(|err| err.at_line(line_number))?;
+        let pts = card.get_winning_points()?;
+
+        total = total.checked_add(pts).ok_or_else(|| {
+            AocError::Validation(format!("total overflows u64 at line {}", line_number))
+        })?;
     }
+    Ok(total)
 }
 
-pub fn solution_1(filename: &str) -> u32 {
-    let file = File::open(filename).expect("failed to open file in solution 1");
+/// Number of instances of each card, originals included, after every card
+/// has won copies of the cards following it.
+///
+/// Copies are propagated with a running difference array, so the cards are
+/// visited once regardless of how many matches they have. Fails when a count
+/// does not fit in a `u64`.
+pub fn card_copies(cards: &[Card]) -> Result<Vec<u64>, AocError> {
+    let overflow =
+        |card: &Card| AocError::Validation(format!("copies of card {} overflow u64", card.id));
 
-    let lines = io::BufReader::new(file).lines();
+    let mut copies = Vec::with_capacity(cards.len());
+    // starting[i] and ending[i] are the inherited copies gained and lost at
+    // card i, kept apart so nothing goes negative
+    let mut starting: Vec<u64> = vec![0; cards.len() + 1];
+    let mut ending: Vec<u64> = vec![0; cards.len() + 1];
+    let mut inherited: u64 = 0;
 
-    let mut total: u32 = 0;
-    for line in lines {
-        if let Ok(ip) = line {
-            let card = Card::from_str(&ip);
-            let pts = card.get_winning_points();
+    for (i, card) in cards.iter().enumerate() {
+        inherited = inherited
+            .checked_add(starting[i])
+            .ok_or_else(|| overflow(card))?
+            - ending[i];
+        let count = inherited.checked_add(1).ok_or_else(|| overflow(card))?;
+        copies.push(count);
 
-            total += pts;
+        let matches = card.get_winning_numbers().len();
+        let last_won = (i + matches).min(cards.len() - 1);
+        if last_won > i {
+            starting[i + 1] = starting[i + 1]
+                .checked_add(count)
+                .ok_or_else(|| overflow(card))?;
+            ending[last_won + 1] = ending[last_won + 1]
+                .checked_add(count)
+                .ok_or_else(|| overflow(card))?;
         }
     }
-    total
+    Ok(copies)
+}
+
+/// Parses every card of the input.
+pub fn parse_cards<R: BufRead>(reader: R) -> Result<Vec<Card>, AocError> {
+    let mut cards = vec![];
+    for line in input::numbered_lines(reader) {
+        let (line_number, ip) = line?;
+        cards.push(Card::from_str(&ip).map_err(|err| err.at_line(line_number))?);
+    }
+    Ok(cards)
+}
+
+pub fn part_2_from_reader<R: BufRead>(reader: R) -> Result<u64, AocError> {
+    let cards = parse_cards(reader)?;
+    card_copies(&cards)?
+        .into_iter()
+        .try_fold(0u64, |total, count| total.checked_add(count))
+        .ok_or_else(|| AocError::Validation("total of card copies overflows u64".to_string()))
+}
+
+pub fn part_1(input: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input.as_bytes())
+}
+
+pub fn part_2(input: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input.as_bytes())
+}
+
+pub fn solution_1(filename: &str) -> Result<u64, AocError> {
+    part_1_from_reader(input::open(filename)?)
+}
+
+pub fn solution_2(filename: &str) -> Result<u64, AocError> {
+    part_2_from_reader(input::open(filename)?)
+}
+
+pub struct DayFour;
+
+impl Solution for DayFour {
+    fn day(&self) -> u8 {
+        4
+    }
+
+    fn part_one(&self, input: &str) -> SolutionResult {
+        Ok(part_1(input)?.into())
+    }
+
+    fn part_two(&self, input: &str) -> SolutionResult {
+        Ok(part_2(input)?.into())
+    }
 }
 
 #[cfg(test)]
@@ -99,7 +178,7 @@ mod test {
     #[test]
     fn card_parse() {
         let line = "Card 159: 13 42 26 92 53 33 44 45 19 90 | 59 95  2 51 25 81 17 30  3 71 36 22 58 90 33 52  8 92 37  6 11 19 45 96 88";
-        let card = Card::from_str(line);
+        let card = Card::from_str(line).unwrap();
         assert_eq!(card.winning_nums[0], 13);
         assert_eq!(card.your_nums[0], 59);
     }
@@ -107,21 +186,88 @@ mod test {
     #[test]
     fn card_my_winning_nums() {
         let line = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
-        let card = Card::from_str(line);
+        let card = Card::from_str(line).unwrap();
         assert_eq!(card.get_winning_numbers().len(), 4);
     }
 
     #[test]
     fn card_my_winning_points() {
         let line = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
-        let card = Card::from_str(line);
-        assert_eq!(card.get_winning_points(), 8);
+        let card = Card::from_str(line).unwrap();
+        assert_eq!(card.get_winning_points().unwrap(), 8);
+    }
+
+    #[test]
+    fn card_parse_errors() {
+        let err = "Card 1: 41 4x | 83".parse::<Card>().err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 12, .. }));
+
+        let err = "Card 1: 41 48 83".parse::<Card>().err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 17, .. }));
+
+        let err = "Card 1: 41 | 83 | 86".parse::<Card>().err().unwrap();
+        assert!(matches!(err, AocError::Parse { column: 17, .. }));
+
+        let card = "Card\t 2 :41 48|83\r".parse::<Card>().unwrap();
+        assert_eq!((card.id(), card.get_winning_points().unwrap()), (2, 0));
+    }
+
+    const EXAMPLE: &str = "\
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+";
+
+    #[test]
+    fn day_4_example() {
+        assert_eq!(part_1(EXAMPLE).unwrap(), 13);
+        assert_eq!(part_2(EXAMPLE).unwrap(), 30);
+    }
+
+    #[test]
+    fn day_4_points_overflow() {
+        let card = |matches: u32| {
+            let numbers: Vec<String> = (1..=matches).map(|n| n.to_string()).collect();
+            format!("Card 1: {0} | {0}\n", numbers.join(" "))
+        };
+        assert!(matches!(part_1(&card(65)), Err(AocError::Validation(_))));
+        assert_eq!(part_1(&card(64)).unwrap(), 1 << 63);
+        assert!(matches!(
+            part_1(&card(64).repeat(2)),
+            Err(AocError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn card_copies_propagate() {
+        let cards = parse_cards(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(card_copies(&cards).unwrap(), vec![1, 2, 4, 8, 14, 1]);
+        assert!(card_copies(&[]).unwrap().is_empty());
+
+        // every card wins a copy of the next five, so counts grow
+        // exponentially and pass u64 long before card 100
+        let winning: String = (1..=100)
+            .map(|id| format!("Card {}: 1 2 3 4 5 | 1 2 3 4 5\n", id))
+            .collect();
+        let cards = parse_cards(winning.as_bytes()).unwrap();
+        assert!(matches!(card_copies(&cards), Err(AocError::Validation(_))));
+        assert!(part_2(&winning).is_err());
     }
 
     #[test]
     fn day_4_part_1() {
         let filename = "data/day_4.txt";
-        let sol = solution_1(&filename);
+        let sol = solution_1(filename).unwrap();
         println!("Day 4 Solution 1: {}", sol);
     }
+
+    #[test]
+    fn day_4_part_2() {
+        let filename = "data/day_4.txt";
+        let sol = solution_2(filename).unwrap();
+        println!("Day 4 Solution 2: {}", sol);
+    }
 }
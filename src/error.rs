@@ -0,0 +1,134 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Error type shared by every day's parsers and solutions.
+#[derive(Debug)]
+pub enum AocError {
+    Io(io::Error),
+    /// Malformed input. `line` and `column` are 1-based, the column counting
+    /// characters rather than bytes. `text` is the offending part of the line.
+    Parse {
+        line: usize,
+        column: usize,
+        text: String,
+        message: String,
+    },
+    /// The requested part of a day has no solution yet.
+    MissingPart {
+        day: u8,
+        part: u8,
+    },
+    /// Well-formed input that the puzzle rules reject.
+    Validation(String),
+}
+
+impl AocError {
+    /// Parse error for `text` within `line`. The column is derived from the
+    /// position of `text` when it is a subslice of `line`, otherwise it is 1.
+    pub fn parse_in(line: &str, text: &str, message: impl Into<String>) -> Self {
+        let line_start = line.as_ptr() as usize;
+        let text_start = text.as_ptr() as usize;
+        let column =
+            if text_start >= line_start && text_start + text.len() <= line_start + line.len() {
+                line[..text_start - line_start].chars().count() + 1
+            } else {
+                1
+            };
+        AocError::Parse {
+            line: 1,
+            column,
+            text: text.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Sets the line number of a parse error, other errors are returned as is.
+    pub fn at_line(self, line_number: usize) -> Self {
+        match self {
+            AocError::Parse {
+                column,
+                text,
+                message,
+                ..
+            } => AocError::Parse {
+                line: line_number,
+                column,
+                text,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for AocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AocError::Io(err) => write!(f, "i/o error: {}", err),
+            AocError::Parse {
+                line,
+                column,
+                text,
+                message,
+            } => write!(
+                f,
+                "line {}, column {}: {} (`{}`)",
+                line, column, message, text
+            ),
+            AocError::MissingPart { day, part } => {
+                write!(f, "day {} part {} is not implemented", day, part)
+            }
+            AocError::Validation(message) => write!(f, "invalid input: {}", message),
+        }
+    }
+}
+
+impl Error for AocError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AocError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AocError {
+    fn from(err: io::Error) -> Self {
+        AocError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_in_locates_column() {
+        let line = "Game 1: x blue";
+        let err = AocError::parse_in(line, &line[8..9], "invalid count").at_line(4);
+        match err {
+            AocError::Parse {
+                line, column, text, ..
+            } => {
+                assert_eq!((line, column), (4, 9));
+                assert_eq!(text, "x");
+            }
+            _ => panic!("expected parse error"),
+        }
+    }
+
+    #[test]
+    fn parse_in_counts_characters() {
+        let line = "zwölf = x";
+        let err = AocError::parse_in(line, &line[9..], "invalid digit");
+        assert!(matches!(err, AocError::Parse { column: 9, .. }));
+    }
+
+    #[test]
+    fn parse_in_unrelated_text() {
+        let text = String::from("xyz");
+        let err = AocError::parse_in("abc", &text, "bad");
+        assert!(matches!(err, AocError::Parse { column: 1, .. }));
+    }
+}
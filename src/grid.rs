@@ -0,0 +1,272 @@
+use crate::error::AocError;
+
+const MOORE: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+const VON_NEUMANN: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+/// Offsets `(d_row, d_col)` considered adjacent to a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The eight surrounding cells, diagonals included.
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
+    /// Any custom stencil.
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighbourhood {
+    pub fn offsets(&self) -> &[(isize, isize)] {
+        match self {
+            Neighbourhood::Moore => &MOORE,
+            Neighbourhood::VonNeumann => &VON_NEUMANN,
+            Neighbourhood::Custom(offsets) => offsets,
+        }
+    }
+}
+
+/// Rectangular grid stored row-major in a single buffer. Positions are
+/// `(row, col)` pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Builds a grid from equally long rows.
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Result<Self, AocError> {
+        let width = rows.first().map_or(0, Vec::len);
+        let height = rows.len();
+        let mut cells = Vec::with_capacity(width * height);
+        for (i, row) in rows.into_iter().enumerate() {
+            if row.len() != width {
+                return Err(AocError::Validation(format!(
+                    "grid row {} has width {}, expected {}",
+                    i + 1,
+                    row.len(),
+                    width
+                )));
+            }
+            cells.extend(row);
+        }
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        if row < self.height && col < self.width {
+            self.cells.get(row * self.width + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        if row < self.height && col < self.width {
+            self.cells.get_mut(row * self.width + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self, row: usize) -> Option<&[T]> {
+        if row < self.height {
+            Some(&self.cells[row * self.width..(row + 1) * self.width])
+        } else {
+            None
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(move |row| &self.cells[row * self.width..(row + 1) * self.width])
+    }
+
+    /// Cells of column `col` from top to bottom, empty when out of bounds.
+    pub fn column(&self, col: usize) -> impl Iterator<Item = &T> {
+        let height = if col < self.width { self.height } else { 0 };
+        (0..height).map(move |row| &self.cells[row * self.width + col])
+    }
+
+    /// Every cell with its position, in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, cell)| ((i / self.width, i % self.width), cell))
+    }
+
+    /// Position `(row + d_row, col + d_col)`, `None` when it falls outside
+    /// the grid.
+    pub fn offset(
+        &self,
+        (row, col): (usize, usize),
+        d_row: isize,
+        d_col: isize,
+    ) -> Option<(usize, usize)> {
+        let row = row.checked_add_signed(d_row)?;
+        let col = col.checked_add_signed(d_col)?;
+        if row < self.height && col < self.width {
+            Some((row, col))
+        } else {
+            None
+        }
+    }
+
+    /// Like `offset`, but leaving one edge re-enters from the opposite one.
+    /// `None` only for an empty grid.
+    pub fn wrapping_offset(
+        &self,
+        (row, col): (usize, usize),
+        d_row: isize,
+        d_col: isize,
+    ) -> Option<(usize, usize)> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let row = (row as isize + d_row).rem_euclid(self.height as isize) as usize;
+        let col = (col as isize + d_col).rem_euclid(self.width as isize) as usize;
+        Some((row, col))
+    }
+
+    /// Positions around `pos` that lie inside the grid.
+    pub fn neighbours<'a>(
+        &'a self,
+        pos: (usize, usize),
+        neighbourhood: &'a Neighbourhood,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        neighbourhood
+            .offsets()
+            .iter()
+            .filter_map(move |&(d_row, d_col)| self.offset(pos, d_row, d_col))
+    }
+
+    /// Positions around `pos` on a grid whose edges wrap around. On grids
+    /// smaller than the stencil the same position can be yielded more than
+    /// once.
+    pub fn neighbours_wrapping<'a>(
+        &'a self,
+        pos: (usize, usize),
+        neighbourhood: &'a Neighbourhood,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        neighbourhood
+            .offsets()
+            .iter()
+            .filter_map(move |&(d_row, d_col)| self.wrapping_offset(pos, d_row, d_col))
+    }
+}
+
+impl Grid<char> {
+    /// Grid of characters, one row per line.
+    pub fn from_lines<I, S>(lines: I) -> Result<Self, AocError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::from_rows(
+            lines
+                .into_iter()
+                .map(|line| line.as_ref().chars().collect())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Grid<char> {
+        Grid::from_lines(["abc", "def"]).unwrap()
+    }
+
+    #[test]
+    fn grid_dimensions_and_get() {
+        let grid = sample();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.get(1, 2), Some(&'f'));
+        assert_eq!(grid.get(2, 0), None);
+        assert_eq!(grid.get(0, 3), None);
+    }
+
+    #[test]
+    fn grid_rows_and_columns() {
+        let grid = sample();
+        let rows: Vec<String> = grid.rows().map(|r| r.iter().collect()).collect();
+        assert_eq!(rows, vec!["abc", "def"]);
+        assert_eq!(grid.row(1), Some(&['d', 'e', 'f'][..]));
+        assert_eq!(grid.column(1).collect::<String>(), "be");
+        assert_eq!(grid.column(3).count(), 0);
+        assert_eq!(grid.iter().nth(4), Some(((1, 1), &'e')));
+    }
+
+    #[test]
+    fn grid_offset_bounds() {
+        let grid = sample();
+        assert_eq!(grid.offset((0, 0), 1, 2), Some((1, 2)));
+        assert_eq!(grid.offset((0, 0), -1, 0), None);
+        assert_eq!(grid.offset((1, 2), 0, 1), None);
+        assert_eq!(grid.offset((1, 2), -1, -2), Some((0, 0)));
+    }
+
+    #[test]
+    fn grid_neighbours_clip_at_edges() {
+        let grid = Grid::from_lines(["abc", "def", "ghi"]).unwrap();
+        let moore: Vec<_> = grid.neighbours((0, 0), &Neighbourhood::Moore).collect();
+        assert_eq!(moore, vec![(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(grid.neighbours((1, 1), &Neighbourhood::Moore).count(), 8);
+
+        let von_neumann: Vec<_> = grid
+            .neighbours((2, 1), &Neighbourhood::VonNeumann)
+            .collect();
+        assert_eq!(von_neumann, vec![(1, 1), (2, 0), (2, 2)]);
+
+        let knight = Neighbourhood::Custom(vec![(2, 1), (-2, 1)]);
+        let custom: Vec<_> = grid.neighbours((0, 0), &knight).collect();
+        assert_eq!(custom, vec![(2, 1)]);
+    }
+
+    #[test]
+    fn grid_neighbours_wrapping() {
+        let grid = Grid::from_lines(["abc", "def", "ghi"]).unwrap();
+        let wrapped: Vec<_> = grid
+            .neighbours_wrapping((0, 0), &Neighbourhood::VonNeumann)
+            .collect();
+        assert_eq!(wrapped, vec![(2, 0), (0, 2), (0, 1), (1, 0)]);
+
+        let empty: Grid<char> = Grid::from_rows(vec![]).unwrap();
+        assert_eq!(
+            empty
+                .neighbours_wrapping((0, 0), &Neighbourhood::Moore)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn grid_rejects_ragged_rows() {
+        let grid = Grid::from_lines(["abc", "de"]);
+        assert!(matches!(grid, Err(AocError::Validation(_))));
+    }
+}
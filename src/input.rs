@@ -0,0 +1,41 @@
+use crate::error::AocError;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Opens a puzzle input file for buffered reading.
+pub fn open(path: impl AsRef<Path>) -> Result<BufReader<File>, AocError> {
+    Ok(BufReader::new(File::open(path)?))
+}
+
+/// Lines of `reader` paired with their 1-based line number.
+pub fn numbered_lines<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<(usize, String), AocError>> {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| Ok((i + 1, line?)))
+}
+
+/// Calls `f` with every line of `reader` and its 1-based line number,
+/// reusing one buffer instead of allocating a `String` per line.
+pub fn for_each_line<R: BufRead>(
+    mut reader: R,
+    mut f: impl FnMut(usize, &str) -> Result<(), AocError>,
+) -> Result<(), AocError> {
+    let mut buf = String::new();
+    let mut line_number = 0;
+    loop {
+        buf.clear();
+        if reader.read_line(&mut buf)? == 0 {
+            return Ok(());
+        }
+        line_number += 1;
+        let line = match buf.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => &buf,
+        };
+        f(line_number, line)?;
+    }
+}
@@ -2,3 +2,43 @@ pub mod day_1;
 pub mod day_2;
 pub mod day_3;
 pub mod day_4;
+pub mod error;
+pub mod grid;
+pub mod input;
+pub mod parser;
+pub mod solution;
+pub mod trie;
+
+pub use error::AocError;
+pub use solution::{Answer, Solution, SolutionResult};
+
+/// Every implemented day, ordered by day number.
+pub const SOLUTIONS: &[&dyn Solution] = &[
+    &day_1::DayOne,
+    &day_2::DayTwo,
+    &day_3::DayThree,
+    &day_4::DayFour,
+];
+
+pub fn get_solution(day: u8) -> Option<&'static dyn Solution> {
+    SOLUTIONS.iter().copied().find(|s| s.day() == day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_days_are_ordered_and_unique() {
+        let days: Vec<u8> = SOLUTIONS.iter().map(|s| s.day()).collect();
+        assert_eq!(days, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn registry_runs_day() {
+        let solution = get_solution(2).expect("day 2 registered");
+        let answer = solution.part_from_path(2, &solution.input_path()).unwrap();
+        assert!(answer.is_ok());
+        assert!(get_solution(25).is_none());
+    }
+}
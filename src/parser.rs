@@ -0,0 +1,149 @@
+use crate::error::AocError;
+
+/// Characters that always form a token of their own.
+const PUNCTUATION: &[char] = &[':', ';', ',', '|'];
+
+/// Cursor over a single input line for the small hand-written grammars of
+/// the puzzles. Tokens are runs of characters separated by whitespace (tabs
+/// and a stray `\r` included) or by punctuation, which stands on its own.
+/// Errors point at the offending token.
+pub struct LineParser<'a> {
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> LineParser<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Self { line, pos: 0 }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.line[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Next token without consuming it, `None` at the end of the line.
+    pub fn peek(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = &self.line[self.pos..];
+        let first = rest.chars().next()?;
+        if PUNCTUATION.contains(&first) {
+            return Some(&rest[..first.len_utf8()]);
+        }
+        let len = rest
+            .find(|c: char| c.is_whitespace() || PUNCTUATION.contains(&c))
+            .unwrap_or(rest.len());
+        Some(&rest[..len])
+    }
+
+    /// Consumes and returns the next token.
+    pub fn next_token(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.pos += token.len();
+        Some(token)
+    }
+
+    pub fn is_at_end(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    /// Consumes the next token if it is `expected`.
+    pub fn eat(&mut self, expected: &str) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += expected.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn expect(&mut self, expected: &str) -> Result<(), AocError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error_here(format!("expected `{}`", expected)))
+        }
+    }
+
+    /// Next token parsed as a number of ASCII digits only, without a sign,
+    /// `what` names it in the error.
+    pub fn number(&mut self, what: &str) -> Result<u32, AocError> {
+        let token = self
+            .peek()
+            .ok_or_else(|| self.error_here(format!("missing {}", what)))?;
+        let value = Some(token)
+            .filter(|token| token.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|token| token.parse::<u32>().ok())
+            .ok_or_else(|| self.error(token, format!("invalid {}", what)))?;
+        self.pos += token.len();
+        Ok(value)
+    }
+
+    /// Next token as a word, anything but punctuation.
+    pub fn word(&mut self, what: &str) -> Result<&'a str, AocError> {
+        match self.peek() {
+            Some(token) if !token.starts_with(PUNCTUATION) => {
+                self.pos += token.len();
+                Ok(token)
+            }
+            _ => Err(self.error_here(format!("expected {}", what))),
+        }
+    }
+
+    /// `<keyword> <id>:` at the start of a line, returning the id.
+    pub fn header(&mut self, keyword: &str) -> Result<u32, AocError> {
+        if !self.eat(keyword) {
+            return Err(self.error_here(format!("expected `{}`", keyword)));
+        }
+        let id = self.number(&format!("{} id", keyword.to_lowercase()))?;
+        self.expect(":")?;
+        Ok(id)
+    }
+
+    /// Parse error for `text`, a subslice of the line.
+    pub fn error(&self, text: &str, message: impl Into<String>) -> AocError {
+        AocError::parse_in(self.line, text, message)
+    }
+
+    /// Parse error at the next token, or at the end of the line.
+    pub fn error_here(&mut self, message: impl Into<String>) -> AocError {
+        let text = self.peek().unwrap_or(&self.line[self.line.len()..]);
+        self.error(text, message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_parser_tokens() {
+        let mut parser = LineParser::new("Game\t12 :3 blue,;\r");
+        let tokens: Vec<&str> = std::iter::from_fn(|| parser.next_token()).collect();
+        assert_eq!(tokens, vec!["Game", "12", ":", "3", "blue", ",", ";"]);
+        assert!(parser.is_at_end());
+    }
+
+    #[test]
+    fn line_parser_header() {
+        let mut parser = LineParser::new("Card   7:  1 2");
+        assert_eq!(parser.header("Card").unwrap(), 7);
+        assert_eq!(parser.number("number").unwrap(), 1);
+
+        let err = LineParser::new("Card x: 1").header("Card").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 6, .. }));
+        let err = LineParser::new("Card 1").header("Card").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 7, .. }));
+        let err = LineParser::new("Game 1:").header("Card").unwrap_err();
+        assert!(matches!(err, AocError::Parse { column: 1, .. }));
+    }
+
+    #[test]
+    fn line_parser_number_is_digits_only() {
+        assert_eq!(LineParser::new("0042").number("n").unwrap(), 42);
+        for line in ["+3", "-3", "3.0", "4294967296"] {
+            let err = LineParser::new(line).number("n").unwrap_err();
+            assert!(matches!(err, AocError::Parse { column: 1, .. }), "{}", line);
+        }
+    }
+}
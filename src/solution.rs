@@ -0,0 +1,74 @@
+use crate::error::AocError;
+use std::fmt;
+use std::fs;
+
+/// The answer to one part of a puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Number(u64),
+    Text(String),
+}
+
+impl From<u32> for Answer {
+    fn from(value: u32) -> Self {
+        Answer::Number(value.into())
+    }
+}
+
+impl From<u64> for Answer {
+    fn from(value: u64) -> Self {
+        Answer::Number(value)
+    }
+}
+
+impl From<String> for Answer {
+    fn from(value: String) -> Self {
+        Answer::Text(value)
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Number(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+pub type SolutionResult = Result<Answer, AocError>;
+
+/// Common interface implemented by every day, so days can be enumerated and
+/// run without knowing the names of their solution functions.
+pub trait Solution {
+    fn day(&self) -> u8;
+
+    fn part_one(&self, input: &str) -> SolutionResult;
+
+    fn part_two(&self, input: &str) -> SolutionResult;
+
+    /// Path of the puzzle input bundled with the repository.
+    fn input_path(&self) -> String {
+        format!("data/day_{}.txt", self.day())
+    }
+
+    /// Runs part `1` or `2` on `input`, `None` for any other part number.
+    fn part(&self, part: u8, input: &str) -> Option<SolutionResult> {
+        match part {
+            1 => Some(self.part_one(input)),
+            2 => Some(self.part_two(input)),
+            _ => None,
+        }
+    }
+
+    /// Runs part `1` or `2` on the contents of the file at `path`.
+    fn part_from_path(&self, part: u8, path: &str) -> Option<SolutionResult> {
+        if part != 1 && part != 2 {
+            return None;
+        }
+        match fs::read_to_string(path) {
+            Ok(input) => self.part(part, &input),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
@@ -0,0 +1,540 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::iter::FromIterator;
+
+const ROOT: usize = 0;
+
+struct TrieNode<V> {
+    children: BTreeMap<char, usize>,
+    value: Option<V>,
+    /// Length in bytes of the key spelled by the path to this node.
+    depth: usize,
+}
+
+impl<V> TrieNode<V> {
+    fn new(depth: usize) -> Self {
+        Self {
+            children: BTreeMap::new(),
+            value: None,
+            depth,
+        }
+    }
+}
+
+/// Map from string keys to values, stored as a trie over `char`s. Nodes live
+/// in one vector and refer to their children by index, with the root at
+/// index 0.
+pub struct Trie<V> {
+    nodes: Vec<TrieNode<V>>,
+    len: usize,
+}
+
+impl<V> Default for Trie<V> {
+    fn default() -> Self {
+        Trie {
+            nodes: vec![TrieNode::new(0)],
+            len: 0,
+        }
+    }
+}
+
+impl<V> Trie<V> {
+    pub fn new() -> Self {
+        Trie::default()
+    }
+
+    /// Number of keys in the trie.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts `key`, returning the value it previously held.
+    pub fn insert(&mut self, key: &str, value: V) -> Option<V> {
+        let mut node = ROOT;
+        for ch in key.chars() {
+            node = match self.child(node, ch) {
+                Some(next_node) => next_node,
+                None => {
+                    let next_node = self.nodes.len();
+                    let depth = self.nodes[node].depth + ch.len_utf8();
+                    self.nodes.push(TrieNode::new(depth));
+                    self.nodes[node].children.insert(ch, next_node);
+                    next_node
+                }
+            };
+        }
+        let previous = self.nodes[node].value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    fn child(&self, node: usize, ch: char) -> Option<usize> {
+        self.nodes[node].children.get(&ch).copied()
+    }
+
+    /// Node reached by spelling `key` from the root.
+    fn node(&self, key: &str) -> Option<usize> {
+        key.chars().try_fold(ROOT, |node, ch| self.child(node, ch))
+    }
+
+    fn is_end_of_word(&self, node: usize) -> bool {
+        self.nodes[node].value.is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.nodes[self.node(key)?].value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        let node = self.node(key)?;
+        self.nodes[node].value.as_mut()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes `key`, returning its value. The nodes of the key stay in the
+    /// trie, only the value is dropped.
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let node = self.node(key)?;
+        let value = self.nodes[node].value.take();
+        if value.is_some() {
+            self.len -= 1;
+        }
+        value
+    }
+
+    /// Whether `word` is a key of the trie.
+    pub fn search(&self, word: &str) -> bool {
+        self.contains_key(word)
+    }
+
+    /// Whether any key occurs somewhere in `s`.
+    pub fn contains_word(&self, s: &str) -> bool {
+        for (start_index, _) in s.char_indices() {
+            if self.search_from(&s[start_index..]) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether some key is a prefix of `s`.
+    pub fn search_from(&self, s: &str) -> bool {
+        self.longest_prefix_match(s).is_some()
+    }
+
+    /// Longest key that is a prefix of `s`, with its value.
+    pub fn longest_prefix_match<'s>(&self, s: &'s str) -> Option<(&'s str, &V)> {
+        let mut node = ROOT;
+        let mut longest = self.nodes[ROOT].value.as_ref().map(|value| ("", value));
+        for ch in s.chars() {
+            match self.child(node, ch) {
+                Some(next_node) => node = next_node,
+                None => break,
+            }
+            if let Some(value) = self.nodes[node].value.as_ref() {
+                longest = Some((&s[..self.nodes[node].depth], value));
+            }
+        }
+        longest
+    }
+
+    /// Keys starting with `prefix` and their values, in lexicographic order
+    /// of `char`s.
+    pub fn iter_prefix(&self, prefix: &str) -> PrefixIter<'_, V> {
+        let stack = match self.node(prefix) {
+            Some(node) => vec![(node, prefix.to_string())],
+            None => vec![],
+        };
+        PrefixIter { trie: self, stack }
+    }
+
+    /// Every key and its value, in lexicographic order of `char`s.
+    pub fn iter(&self) -> PrefixIter<'_, V> {
+        self.iter_prefix("")
+    }
+
+    /// Match starting furthest left, the shortest one when several keys
+    /// start at the same position.
+    pub fn find_match<'t>(&'t self, s: &str) -> Option<Match<'t, V>> {
+        for (start, _) in s.char_indices() {
+            let mut node = ROOT;
+            for ch in s[start..].chars() {
+                match self.child(node, ch) {
+                    Some(next_node) => node = next_node,
+                    None => break,
+                }
+                if let Some(value) = self.nodes[node].value.as_ref() {
+                    return Some(Match {
+                        start,
+                        end: start + self.nodes[node].depth,
+                        value,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Every key as a `String`, in lexicographic order of `char`s.
+    pub fn words(&self) -> Vec<String> {
+        self.iter().map(|(key, _)| key).collect()
+    }
+
+    pub fn automaton(&self) -> AhoCorasick<'_, V> {
+        AhoCorasick::new(self)
+    }
+}
+
+impl<V: Clone> Trie<V> {
+    /// Trie of the same keys spelled backwards, for searching from the end
+    /// of a string.
+    pub fn reversed(&self) -> ReversedTrie<V> {
+        self.iter()
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+}
+
+impl<K: AsRef<str>, V> FromIterator<(K, V)> for Trie<V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut trie = Trie::new();
+        for (key, value) in iter {
+            trie.insert(key.as_ref(), value);
+        }
+        trie
+    }
+}
+
+/// Depth first iterator over the keys below a node, see `Trie::iter_prefix`.
+pub struct PrefixIter<'t, V> {
+    trie: &'t Trie<V>,
+    stack: Vec<(usize, String)>,
+}
+
+impl<'t, V> Iterator for PrefixIter<'t, V> {
+    type Item = (String, &'t V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, key)) = self.stack.pop() {
+            // reversed so the smallest child is popped first
+            for (&ch, &child) in self.trie.nodes[node].children.iter().rev() {
+                let mut child_key = key.clone();
+                child_key.push(ch);
+                self.stack.push((child, child_key));
+            }
+            if let Some(value) = self.trie.nodes[node].value.as_ref() {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
+
+/// Trie storing its keys reversed. Keys are inserted and reported in their
+/// normal spelling.
+pub struct ReversedTrie<V> {
+    trie: Trie<V>,
+}
+
+impl<V> Default for ReversedTrie<V> {
+    fn default() -> Self {
+        ReversedTrie { trie: Trie::new() }
+    }
+}
+
+impl<V> ReversedTrie<V> {
+    pub fn new() -> Self {
+        ReversedTrie::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trie.is_empty()
+    }
+
+    pub fn insert(&mut self, key: &str, value: V) -> Option<V> {
+        let reversed: String = key.chars().rev().collect();
+        self.trie.insert(&reversed, value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        let reversed: String = key.chars().rev().collect();
+        self.trie.get(&reversed)
+    }
+
+    pub fn search(&self, word: &str) -> bool {
+        self.get(word).is_some()
+    }
+
+    /// Match starting furthest right, the longest one when several keys
+    /// start at the same position, which is the last digit of a decoded
+    /// calibration line. The string is walked from its end, so overlaps such
+    /// as the `eight` in `oneight` are found without rescanning.
+    pub fn rfind_match<'t>(&'t self, s: &str) -> Option<Match<'t, V>> {
+        let mut best: Option<Match<'t, V>> = None;
+        let mut end = s.len();
+        // a key ending at or before the best start cannot start after it
+        while end > best.map_or(0, |found| found.start) {
+            let mut node = ROOT;
+            for ch in s[..end].chars().rev() {
+                match self.trie.child(node, ch) {
+                    Some(next_node) => node = next_node,
+                    None => break,
+                }
+                if let Some(value) = self.trie.nodes[node].value.as_ref() {
+                    let start = end - self.trie.nodes[node].depth;
+                    // ends are tried right to left, so the first match
+                    // found at a start is the longest one there
+                    if best.is_none_or(|found| start > found.start) {
+                        best = Some(Match { start, end, value });
+                    }
+                }
+            }
+            end -= s[..end].chars().next_back().map_or(1, char::len_utf8);
+        }
+        best
+    }
+}
+
+impl<K: AsRef<str>, V> FromIterator<(K, V)> for ReversedTrie<V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut trie = ReversedTrie::new();
+        for (key, value) in iter {
+            trie.insert(key.as_ref(), value);
+        }
+        trie
+    }
+}
+
+/// A key found in a scanned string, as a byte range, with the key's value.
+#[derive(Debug)]
+pub struct Match<'t, V> {
+    pub start: usize,
+    pub end: usize,
+    pub value: &'t V,
+}
+
+impl<V> Clone for Match<'_, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for Match<'_, V> {}
+
+impl<V: PartialEq> PartialEq for Match<'_, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end && self.value == other.value
+    }
+}
+
+impl<V: Eq> Eq for Match<'_, V> {}
+
+impl<V> Match<'_, V> {
+    pub fn as_str<'s>(&self, haystack: &'s str) -> &'s str {
+        &haystack[self.start..self.end]
+    }
+}
+
+/// Aho-Corasick automaton built from a `Trie`. Finds every occurrence of
+/// every key, overlapping ones included, in a single pass over the input.
+pub struct AhoCorasick<'t, V> {
+    trie: &'t Trie<V>,
+    /// Longest proper suffix of a node's key that is also a trie path.
+    fail: Vec<usize>,
+    /// Nearest node on the failure chain, excluding the node itself, that
+    /// ends a key.
+    output: Vec<Option<usize>>,
+}
+
+impl<'t, V> AhoCorasick<'t, V> {
+    pub fn new(trie: &'t Trie<V>) -> Self {
+        let mut fail = vec![ROOT; trie.nodes.len()];
+        let mut output = vec![None; trie.nodes.len()];
+
+        // breadth first, so a node's failure link is known before its children
+        let mut queue: VecDeque<usize> = trie.nodes[ROOT].children.values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            for (&ch, &child) in trie.nodes[node].children.iter() {
+                let mut state = fail[node];
+                let child_fail = loop {
+                    match trie.child(state, ch) {
+                        Some(next) if next != child => break next,
+                        _ if state == ROOT => break ROOT,
+                        _ => state = fail[state],
+                    }
+                };
+                fail[child] = child_fail;
+                output[child] = if trie.is_end_of_word(child_fail) {
+                    Some(child_fail)
+                } else {
+                    output[child_fail]
+                };
+                queue.push_back(child);
+            }
+        }
+
+        Self { trie, fail, output }
+    }
+
+    fn next_state(&self, mut state: usize, ch: char) -> usize {
+        loop {
+            if let Some(next) = self.trie.child(state, ch) {
+                return next;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.fail[state];
+        }
+    }
+
+    /// Every match in `haystack`, ordered by end position and, for matches
+    /// ending at the same position, longest first.
+    pub fn find_all(&self, haystack: &str) -> Vec<Match<'t, V>> {
+        let mut matches = vec![];
+        let mut state = ROOT;
+        for (i, ch) in haystack.char_indices() {
+            state = self.next_state(state, ch);
+            let end = i + ch.len_utf8();
+
+            let mut node = if self.trie.is_end_of_word(state) {
+                Some(state)
+            } else {
+                self.output[state]
+            };
+            while let Some(found) = node {
+                let found_node = &self.trie.nodes[found];
+                if let Some(value) = found_node.value.as_ref() {
+                    matches.push(Match {
+                        start: end - found_node.depth,
+                        end,
+                        value,
+                    });
+                }
+                node = self.output[found];
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Trie<u32> {
+        [("tea", 1), ("ten", 2), ("to", 3), ("inn", 4), ("in", 5)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn trie_map_operations() {
+        let mut trie = sample();
+        assert_eq!(trie.len(), 5);
+        assert_eq!(trie.get("ten"), Some(&2));
+        assert_eq!(trie.get("te"), None);
+        assert!(trie.search("in"));
+        assert!(!trie.search("i"));
+
+        assert_eq!(trie.insert("ten", 10), Some(2));
+        assert_eq!(trie.len(), 5);
+        *trie.get_mut("to").unwrap() += 30;
+        assert_eq!(trie.get("to"), Some(&33));
+
+        assert_eq!(trie.remove("in"), Some(5));
+        assert_eq!(trie.remove("in"), None);
+        assert_eq!(trie.len(), 4);
+        assert!(!trie.contains_key("in"));
+        assert!(trie.contains_key("inn"));
+    }
+
+    #[test]
+    fn trie_iter_prefix() {
+        let trie = sample();
+        let keys: Vec<(String, u32)> = trie
+            .iter_prefix("te")
+            .map(|(key, &value)| (key, value))
+            .collect();
+        assert_eq!(keys, vec![("tea".to_string(), 1), ("ten".to_string(), 2)]);
+        assert_eq!(trie.iter_prefix("x").count(), 0);
+        assert_eq!(trie.words(), vec!["in", "inn", "tea", "ten", "to"]);
+    }
+
+    #[test]
+    fn trie_longest_prefix_match() {
+        let trie = sample();
+        assert_eq!(trie.longest_prefix_match("innkeeper"), Some(("inn", &4)));
+        assert_eq!(trie.longest_prefix_match("ink"), Some(("in", &5)));
+        assert_eq!(trie.longest_prefix_match("tx"), None);
+        assert!(trie.search_from("tears"));
+        assert!(trie.contains_word("stone"));
+        assert!(!trie.contains_word("xyz"));
+    }
+
+    #[test]
+    fn trie_find_match() {
+        let trie = sample();
+        let found = trie.find_match("attend").unwrap();
+        assert_eq!((found.start, found.end, *found.value), (2, 5, 2));
+        assert!(trie.find_match("zzz").is_none());
+    }
+
+    #[test]
+    fn reversed_trie_rfind_match() {
+        let reversed = sample().reversed();
+        assert_eq!(reversed.len(), 5);
+        assert_eq!(reversed.get("inn"), Some(&4));
+        let s = "tea in tent";
+        let found = reversed.rfind_match(s).unwrap();
+        assert_eq!((found.as_str(s), *found.value), ("ten", 2));
+
+        // `abcd` ends further right, but `c` starts further right
+        let reversed: ReversedTrie<u32> = [("abcd", 1), ("c", 2), ("cd", 3)].into_iter().collect();
+        let found = reversed.rfind_match("abcd").unwrap();
+        assert_eq!((found.start, found.end, *found.value), (2, 4, 3));
+        let found = reversed.rfind_match("abcdx").unwrap();
+        assert_eq!(found.as_str("abcdx"), "cd");
+    }
+
+    #[test]
+    fn aho_corasick_finds_overlapping_matches() {
+        let trie: Trie<()> = ["he", "she", "his", "hers"]
+            .into_iter()
+            .map(|word| (word, ()))
+            .collect();
+        let automaton = trie.automaton();
+        let haystack = "ushers";
+        let found: Vec<&str> = automaton
+            .find_all(haystack)
+            .iter()
+            .map(|m| m.as_str(haystack))
+            .collect();
+        assert_eq!(found, vec!["she", "he", "hers"]);
+    }
+
+    #[test]
+    fn aho_corasick_skips_removed_keys() {
+        let mut trie = sample();
+        trie.remove("in");
+        let automaton = trie.automaton();
+        let values: Vec<u32> = automaton
+            .find_all("tinn")
+            .iter()
+            .map(|m| *m.value)
+            .collect();
+        assert_eq!(values, vec![4]);
+    }
+}